@@ -7,8 +7,16 @@ use std::{fs::File, path::PathBuf};
 use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
 use serde::Deserialize;
 
+include!("src/deb_cmd.rs");
 include!("src/dirs_config.rs");
+include!("src/env_cmd.rs");
+include!("src/export_cmd.rs");
+include!("src/export_script_cmd.rs");
+include!("src/inspect_cmd.rs");
 include!("src/install_cmd.rs");
+include!("src/keygen_cmd.rs");
+include!("src/pkg_arch_cmd.rs");
+include!("src/sbom_cmd.rs");
 include!("src/uninstall.rs");
 include!("src/tarball_cmd.rs");
 include!("src/opts.rs");
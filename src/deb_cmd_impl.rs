@@ -0,0 +1,263 @@
+use std::{
+    fmt::Write as _,
+    fs::{self, File},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use camino::Utf8Path;
+use clap::Args;
+use color_eyre::{
+    eyre::{bail, ensure, Context, ContextCompat},
+    Result,
+};
+use colored::Colorize;
+use log::info;
+use md5::{Digest, Md5};
+
+use crate::{
+    install_spec::InstallSpec,
+    staging::{BuildOptions, StagedPackage},
+    tarball::{append_data, gzip_archive},
+};
+
+include!("deb_cmd.rs");
+
+// The fields of DEBIAN/control
+struct Control {
+    package: String,
+    version: String,
+    architecture: String,
+    maintainer: String,
+    description: String,
+    homepage: Option<String>,
+}
+
+impl Control {
+    fn validate(&self) -> Result<()> {
+        ensure!(
+            self.package.len() >= 2
+                && self
+                    .package
+                    .starts_with(|c: char| c.is_ascii_alphanumeric())
+                && self.package.chars().all(|c| {
+                    c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '+' | '-' | '.')
+                }),
+            concat!(
+                "{} is not a valid Debian package name, it must only contain lowercase",
+                " letters, digits and the characters + - ."
+            ),
+            self.package
+        );
+        ensure!(
+            self.version.starts_with(|c: char| c.is_ascii_digit())
+                && self.version.chars().all(|c| {
+                    c.is_ascii_alphanumeric() || matches!(c, '.' | '+' | '-' | '~' | ':')
+                }),
+            "{} is not a valid Debian version, it must start with a digit",
+            self.version
+        );
+        ensure!(
+            !self.maintainer.is_empty() && !self.maintainer.contains('\n'),
+            "the maintainer of {} must be a single line",
+            self.package
+        );
+        ensure!(
+            self.description
+                .lines()
+                .next()
+                .is_some_and(|synopsis| !synopsis.trim().is_empty()),
+            "the description of {} must not be empty",
+            self.package
+        );
+
+        Ok(())
+    }
+
+    fn render(
+        &self,
+        installed_size: u64,
+    ) -> String {
+        let mut res = String::new();
+        writeln!(res, "Package: {}", self.package).unwrap();
+        writeln!(res, "Version: {}", self.version).unwrap();
+        writeln!(res, "Architecture: {}", self.architecture).unwrap();
+        writeln!(res, "Maintainer: {}", self.maintainer).unwrap();
+        // The size is in KiB
+        writeln!(res, "Installed-Size: {}", installed_size.div_ceil(1024)).unwrap();
+        if let Some(homepage) = &self.homepage {
+            writeln!(res, "Homepage: {homepage}").unwrap();
+        }
+        // The first line is the synopsis, the others are the extended description
+        let mut lines = self.description.trim().lines();
+        writeln!(res, "Description: {}", lines.next().unwrap().trim()).unwrap();
+        for line in lines {
+            if line.trim().is_empty() {
+                res.push_str(" .\n");
+            } else {
+                writeln!(res, " {}", line.trim_end()).unwrap();
+            }
+        }
+
+        res
+    }
+
+    // The epoch is not part of the filename
+    fn filename(&self) -> String {
+        let version = self
+            .version
+            .split_once(':')
+            .map_or(self.version.as_str(), |(_, version)| version);
+        format!("{}_{version}_{}.deb", self.package, self.architecture)
+    }
+}
+
+/// Returns the Debian architecture of the target triple, or of the host
+fn deb_architecture(rust_target_triple: Option<&str>) -> Result<String> {
+    let (arch, hard_float, little_endian) = if let Some(triple) = rust_target_triple {
+        (
+            triple.split('-').next().unwrap(),
+            triple.ends_with("hf"),
+            !triple.starts_with("powerpc64-"),
+        )
+    } else {
+        (
+            std::env::consts::ARCH,
+            cfg!(target_abi = "eabihf"),
+            cfg!(target_endian = "little"),
+        )
+    };
+
+    Ok(match arch {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "x86" | "i386" | "i586" | "i686" => "i386",
+        arch if arch.starts_with("arm") && hard_float => "armhf",
+        arch if arch.starts_with("arm") => "armel",
+        "powerpc64le" => "ppc64el",
+        "powerpc64" if little_endian => "ppc64el",
+        "riscv64" | "riscv64gc" => "riscv64",
+        "s390x" => "s390x",
+        "loongarch64" => "loong64",
+        _ => bail!("unable to detect the Debian architecture of {arch}, use --architecture"),
+    }
+    .to_string())
+}
+
+fn md5sums(staged: &StagedPackage) -> Result<String> {
+    let mut res = String::new();
+    for entry in staged.entries()? {
+        let path = staged.root().join(&entry);
+        if !path.is_symlink() && path.is_file() {
+            let contents =
+                fs::read(&path).with_context(|| format!("unable to read file {path}"))?;
+            writeln!(res, "{:x}  {entry}", Md5::digest(contents)).unwrap();
+        }
+    }
+
+    Ok(res)
+}
+
+fn write_deb(
+    control: &Control,
+    staged: &StagedPackage,
+    filename: &Utf8Path,
+) -> Result<()> {
+    let mut control_archive = tar::Builder::new(Vec::new());
+    append_data(
+        &mut control_archive,
+        Utf8Path::new("control"),
+        control.render(staged.installed_size()?).as_bytes(),
+    )?;
+    append_data(
+        &mut control_archive,
+        Utf8Path::new("md5sums"),
+        md5sums(staged)?.as_bytes(),
+    )?;
+    let conffiles = staged
+        .manifest
+        .config_files()
+        .map(|path| format!("{path}\n"))
+        .collect::<String>();
+    if !conffiles.is_empty() {
+        append_data(
+            &mut control_archive,
+            Utf8Path::new("conffiles"),
+            conffiles.as_bytes(),
+        )?;
+    }
+
+    let mut data_archive = tar::Builder::new(Vec::new());
+    staged.append_to_archive(&mut data_archive)?;
+
+    // A .deb is an ar archive containing these three members, in this order
+    let mtime = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let mut deb = ar::Builder::new(
+        File::create(filename).with_context(|| format!("unable to create file {filename}"))?,
+    );
+    for (name, contents) in [
+        ("debian-binary", b"2.0\n".to_vec()),
+        ("control.tar.gz", gzip_archive(control_archive)?),
+        ("data.tar.gz", gzip_archive(data_archive)?),
+    ] {
+        let mut header = ar::Header::new(name.as_bytes().to_vec(), contents.len() as u64);
+        header.set_mode(0o100644);
+        header.set_mtime(mtime);
+        deb.append(&header, contents.as_slice())
+            .with_context(|| format!("unable to write {name} into {filename}"))?;
+    }
+
+    Ok(())
+}
+
+impl DebCmd {
+    pub fn run(self) -> Result<()> {
+        let package_dir = Utf8Path::from_path(&self.package_dir)
+            .with_context(|| format!("{:?} contains invalid UTF-8 characters", self.package_dir))?;
+        let output_dir = Utf8Path::from_path(&self.output_dir)
+            .with_context(|| format!("{:?} contains invalid UTF-8 characters", self.output_dir))?;
+        let architecture = if let Some(architecture) = &self.architecture {
+            architecture.clone()
+        } else {
+            deb_architecture(self.rust_target_triple.as_deref())?
+        };
+
+        let install_spec = InstallSpec::new_from_path(package_dir)?;
+        for package in install_spec.packages(&self.packages) {
+            let name = package.name.unwrap();
+            let control = Control {
+                version: self.pkg_version.clone().or(package.version).with_context(|| {
+                    format!("package {name} has no version, add it to install.yml or use --pkg-version")
+                })?,
+                maintainer: self.maintainer.clone().or(package.maintainer).with_context(|| {
+                    format!("package {name} has no maintainer, add it to install.yml or use --maintainer")
+                })?,
+                description: self.description.clone().or(package.description).with_context(|| {
+                    format!("package {name} has no description, add it to install.yml or use --description")
+                })?,
+                homepage: package.homepage,
+                architecture: architecture.clone(),
+                package: name,
+            };
+            control.validate()?;
+
+            let staged = StagedPackage::new(
+                StagedPackage::system_dirs(),
+                self.package_dir.clone(),
+                &control.package,
+                BuildOptions {
+                    rust_debug_target: self.rust_debug_target,
+                    rust_profile: self.rust_profile.clone(),
+                    rust_target_triple: self.rust_target_triple.clone(),
+                    build_dir: self.build_dir.clone(),
+                },
+            )?;
+            let filename = output_dir.join(control.filename());
+            info!("Writing {}", filename.as_str().cyan().bold());
+            write_deb(&control, &staged, &filename)?;
+        }
+
+        Ok(())
+    }
+}
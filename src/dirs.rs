@@ -2,9 +2,11 @@ use std::env;
 
 use camino::{Utf8Path, Utf8PathBuf};
 use color_eyre::{eyre::ensure, Result};
+use serde::{Deserialize, Serialize};
 
-use crate::DirsConfig;
+use crate::{dirs_config_impl::ManCompression, DirsConfig};
 
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Dirs {
     pub prefix: Option<Utf8PathBuf>,
     pub exec_prefix: Option<Utf8PathBuf>,
@@ -22,6 +24,9 @@ pub struct Dirs {
     pub mandir: Option<Utf8PathBuf>,
     pub pam_modulesdir: Option<Utf8PathBuf>,
     pub systemd_unitsdir: Utf8PathBuf,
+    // Not a directory, but it changes the name of the installed man pages
+    #[serde(skip)]
+    pub man_compression: ManCompression,
 }
 
 impl Dirs {
@@ -46,6 +51,7 @@ impl Dirs {
             mandir: dirs_config.mandir.map(Utf8PathBuf::from),
             pam_modulesdir: dirs_config.pam_modulesdir.map(Utf8PathBuf::from),
             systemd_unitsdir: Utf8PathBuf::from(dirs_config.systemd_unitsdir.unwrap()),
+            man_compression: dirs_config.man_compression.unwrap_or_default(),
         };
 
         if system {
@@ -80,6 +86,15 @@ impl Dirs {
             runstatedir,
             systemd_unitsdir
         );
+
+        for dir in [&mut self.includedir, &mut self.docdir, &mut self.mandir]
+            .into_iter()
+            .flatten()
+        {
+            if dir.is_relative() {
+                *dir = Utf8Path::new(home).join(&*dir);
+            }
+        }
     }
 
     /// Check that all paths are absolute
@@ -1,4 +1,4 @@
-#[derive(Args, Deserialize, Clone)]
+#[derive(Args, Deserialize, Clone, Default)]
 pub struct DirsConfig {
     #[clap(
         long,
@@ -94,28 +94,24 @@ pub struct DirsConfig {
     pub runstatedir: Option<String>,
     #[clap(
         long,
-        requires = "system",
         env,
         help = concat!("The directory for installing header files to be included by user programs",
-                       " with the C ‘#include’ preprocessor directive. (system only)",
-                       " [default: @prefix@/include]")
+                       " with the C ‘#include’ preprocessor directive.",
+                       " [system default: @prefix@/include] [user default: $HOME/.local/include]")
     )]
     pub includedir: Option<String>,
     #[clap(
         long,
-        requires = "system",
         env,
         help = concat!("The directory for installing documentation files (other than Info)",
-                       " The package name will be appendend automatically. (system only)",
+                       " The package name will be appendend automatically.",
                        " [default: @datarootdir@/doc]")
     )]
     pub docdir: Option<String>,
     #[clap(
         long,
-        requires = "system",
         env,
         help = concat!("The top-level directory for installing the man pages (if any)",
-                       " (system only)",
                        " [default: @datarootdir@/man]")
     )]
     pub mandir: Option<String>,
@@ -135,4 +131,23 @@ pub struct DirsConfig {
                        "[default: @libdir@/systemd]")
     )]
     pub systemd_unitsdir: Option<String>,
+    #[clap(
+        long,
+        value_enum,
+        env,
+        help = concat!("The compression applied to the installed man pages, appending its",
+                       " extension to their name.",
+                       " [default: none]")
+    )]
+    pub man_compression: Option<ManCompression>,
+}
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ManCompression {
+    #[default]
+    None,
+    Gzip,
+    Xz,
+    Zstd,
 }
@@ -1,11 +1,18 @@
 use std::fs;
+use std::io::{Read, Write};
 
 use camino::Utf8PathBuf;
-use clap::Args;
+use clap::{Args, ValueEnum};
 use color_eyre::{
     eyre::{ensure, ContextCompat, WrapErr},
     Result,
 };
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use lzma_rust2::{XzOptions, XzReader, XzWriter};
+use ruzstd::{
+    decoding::StreamingDecoder,
+    encoding::{compress_to_vec, CompressionLevel},
+};
 use serde::Deserialize;
 use xdg::BaseDirectories;
 
@@ -33,11 +40,7 @@ impl DirsConfig {
         system: bool,
         opts: &mut Self,
     ) -> Result<Self> {
-        let mut dirs_config = if system {
-            Self::system_config()
-        } else {
-            Self::user_config()
-        };
+        let mut dirs_config = Self::defaults(system);
 
         let config_file = if let Some(config_file) = config {
             let config_file = Utf8PathBuf::from(config_file);
@@ -55,13 +58,41 @@ impl DirsConfig {
             )?;
             dirs_config.merge(system, config_from_file);
         }
-        opts.sanitize();
-        dirs_config.merge(system, opts.clone());
-        dirs_config.replace_placeholders(system)?;
+        dirs_config.apply_opts(system, opts)?;
+
+        Ok(dirs_config)
+    }
+
+    /// Load the default directories overridden by the options, ignoring the
+    /// configuration files, i.e. when building the packages of a distribution
+    pub fn load_defaults(
+        system: bool,
+        opts: &mut Self,
+    ) -> Result<Self> {
+        let mut dirs_config = Self::defaults(system);
+        dirs_config.apply_opts(system, opts)?;
 
         Ok(dirs_config)
     }
 
+    fn defaults(system: bool) -> Self {
+        if system {
+            Self::system_config()
+        } else {
+            Self::user_config()
+        }
+    }
+
+    fn apply_opts(
+        &mut self,
+        system: bool,
+        opts: &mut Self,
+    ) -> Result<()> {
+        opts.sanitize();
+        self.merge(system, opts.clone());
+        self.replace_placeholders(system)
+    }
+
     #[must_use]
     pub fn system_config() -> Self {
         Self {
@@ -81,6 +112,7 @@ impl DirsConfig {
             mandir: Some("@datarootdir@man/".to_string()),
             pam_modulesdir: Some("@libdir@security/".to_string()),
             systemd_unitsdir: Some("@libdir@systemd/".to_string()),
+            man_compression: None,
         }
     }
 
@@ -98,11 +130,12 @@ impl DirsConfig {
             sysconfdir: Some("@XDG_CONFIG_HOME@/".to_string()),
             localstatedir: Some("@XDG_DATA_HOME@/".to_string()),
             runstatedir: Some("@XDG_RUNTIME_DIR@/".to_string()),
-            includedir: None,
-            docdir: None,
-            mandir: None,
+            includedir: Some("@prefix@include/".to_string()),
+            docdir: Some("@datarootdir@doc/".to_string()),
+            mandir: Some("@datarootdir@man/".to_string()),
             pam_modulesdir: None,
             systemd_unitsdir: Some("@sysconfdir@/systemd/".to_string()),
+            man_compression: None,
         }
     }
 
@@ -140,7 +173,8 @@ impl DirsConfig {
             docdir,
             mandir,
             pam_modulesdir,
-            systemd_unitsdir
+            systemd_unitsdir,
+            man_compression
         );
     }
 
@@ -159,7 +193,11 @@ impl DirsConfig {
             sysconfdir,
             localstatedir,
             runstatedir,
-            systemd_unitsdir
+            includedir,
+            docdir,
+            mandir,
+            systemd_unitsdir,
+            man_compression
         );
     }
 
@@ -238,6 +276,15 @@ impl DirsConfig {
         }
 
         replace!(datarootdir, "@XDG_DATA_HOME@", xdg.get_data_home());
+        // The prefix of the user installations, relative to the home directory
+        replace!(includedir, "@prefix@", Utf8PathBuf::from(".local/"));
+        // The configuration file might omit the ending slash, i.e. @datarootdir@man
+        let datarootdir = Utf8PathBuf::from(format!(
+            "{}/",
+            self.datarootdir.as_ref().unwrap().trim_end_matches('/')
+        ));
+        replace!(docdir, "@datarootdir@", datarootdir);
+        replace!(mandir, "@datarootdir@", datarootdir);
         replace!(datadir, "@XDG_DATA_HOME@", xdg.get_data_home());
         replace!(sysconfdir, "@XDG_CONFIG_HOME@", xdg.get_config_home());
         replace!(localstatedir, "@XDG_DATA_HOME@", xdg.get_data_home());
@@ -284,3 +331,55 @@ impl DirsConfig {
         );
     }
 }
+
+impl ManCompression {
+    /// The extension appended to the name of the compressed files
+    pub const fn extension(self) -> Option<&'static str> {
+        match self {
+            ManCompression::None => None,
+            ManCompression::Gzip => Some("gz"),
+            ManCompression::Xz => Some("xz"),
+            ManCompression::Zstd => Some("zst"),
+        }
+    }
+
+    pub fn compress(
+        self,
+        data: &[u8],
+    ) -> Result<Vec<u8>> {
+        Ok(match self {
+            ManCompression::None => data.to_vec(),
+            ManCompression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+                encoder.write_all(data)?;
+                encoder.finish()?
+            }
+            ManCompression::Xz => {
+                let mut writer = XzWriter::new(Vec::new(), XzOptions::with_preset(6))?;
+                writer.write_all(data)?;
+                writer.finish()?
+            }
+            ManCompression::Zstd => compress_to_vec(data, CompressionLevel::Fastest),
+        })
+    }
+
+    pub fn decompress(
+        self,
+        data: &[u8],
+    ) -> Result<Vec<u8>> {
+        let mut decompressed = Vec::new();
+        match self {
+            ManCompression::None => decompressed.extend_from_slice(data),
+            ManCompression::Gzip => {
+                GzDecoder::new(data).read_to_end(&mut decompressed)?;
+            }
+            ManCompression::Xz => {
+                XzReader::new(data, true).read_to_end(&mut decompressed)?;
+            }
+            ManCompression::Zstd => {
+                StreamingDecoder::new(data)?.read_to_end(&mut decompressed)?;
+            }
+        }
+        Ok(decompressed)
+    }
+}
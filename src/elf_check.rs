@@ -0,0 +1,151 @@
+use std::fmt;
+
+use camino::Utf8Path;
+use color_eyre::{eyre::eyre, Result};
+use object::{
+    elf,
+    read::elf::{Dyn, FileHeader},
+    Endianness, FileKind,
+};
+
+/// An issue found in an ELF executable or library
+pub enum ElfIssue {
+    // The file has been built for another machine than the one expected
+    WrongMachine { found: u16, expected: u16 },
+    // The file looks for its libraries in the build directory
+    BuildDirRpath(String),
+    // The shared library cannot be referenced by its SONAME
+    MissingSoname,
+}
+
+impl fmt::Display for ElfIssue {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self {
+            ElfIssue::WrongMachine { found, expected } => write!(
+                f,
+                "it has been built for {}, expected {}",
+                machine_name(*found),
+                machine_name(*expected)
+            ),
+            ElfIssue::BuildDirRpath(rpath) => {
+                write!(
+                    f,
+                    "its RPATH/RUNPATH {rpath} points into the build directory"
+                )
+            }
+            ElfIssue::MissingSoname => write!(f, "the shared library has no SONAME"),
+        }
+    }
+}
+
+// The machines supported by rust, by the first component of the target triple
+const MACHINES: [(&str, u16, &str); 14] = [
+    ("x86_64", elf::EM_X86_64, "x86_64"),
+    ("i386", elf::EM_386, "x86"),
+    ("i586", elf::EM_386, "x86"),
+    ("i686", elf::EM_386, "x86"),
+    ("x86", elf::EM_386, "x86"),
+    ("aarch64", elf::EM_AARCH64, "aarch64"),
+    ("arm", elf::EM_ARM, "arm"),
+    ("riscv", elf::EM_RISCV, "riscv"),
+    ("powerpc64", elf::EM_PPC64, "powerpc64"),
+    ("powerpc", elf::EM_PPC, "powerpc"),
+    ("s390x", elf::EM_S390, "s390x"),
+    ("mips", elf::EM_MIPS, "mips"),
+    ("loongarch64", elf::EM_LOONGARCH, "loongarch64"),
+    ("sparc64", elf::EM_SPARCV9, "sparc64"),
+];
+
+fn machine_name(machine: u16) -> String {
+    MACHINES
+        .iter()
+        .find(|(_, em, _)| *em == machine)
+        .map_or_else(
+            || format!("machine {machine}"),
+            |(_, _, name)| name.to_string(),
+        )
+}
+
+/// The ELF machine of a target triple, or of the host when it is None
+pub fn expected_machine(rust_target_triple: Option<&str>) -> Option<u16> {
+    let arch = rust_target_triple
+        .and_then(|triple| triple.split('-').next())
+        .unwrap_or(std::env::consts::ARCH);
+    // i.e. armv7, riscv64gc, mips64el
+    MACHINES
+        .iter()
+        .filter(|(prefix, _, _)| arch.starts_with(prefix))
+        .max_by_key(|(prefix, _, _)| prefix.len())
+        .map(|(_, machine, _)| *machine)
+}
+
+/// Check an executable or a library, the files that are not ELF are skipped
+pub fn check_elf(
+    data: &[u8],
+    is_library: bool,
+    expected_machine: Option<u16>,
+    build_dirs: &[&Utf8Path],
+) -> Result<Vec<ElfIssue>> {
+    let res = match FileKind::parse(data) {
+        Ok(FileKind::Elf32) => {
+            check::<elf::FileHeader32<Endianness>>(data, is_library, expected_machine, build_dirs)
+        }
+        Ok(FileKind::Elf64) => {
+            check::<elf::FileHeader64<Endianness>>(data, is_library, expected_machine, build_dirs)
+        }
+        _ => return Ok(Vec::new()),
+    };
+
+    res.map_err(|err| eyre!("unable to parse ELF file: {err}"))
+}
+
+fn check<Elf: FileHeader<Endian = Endianness>>(
+    data: &[u8],
+    is_library: bool,
+    expected_machine: Option<u16>,
+    build_dirs: &[&Utf8Path],
+) -> object::read::Result<Vec<ElfIssue>> {
+    let header = Elf::parse(data)?;
+    let endian = header.endian()?;
+    let mut issues = Vec::new();
+
+    let machine = header.e_machine(endian);
+    if let Some(expected) = expected_machine {
+        if machine != expected {
+            issues.push(ElfIssue::WrongMachine {
+                found: machine,
+                expected,
+            });
+        }
+    }
+
+    let sections = header.sections(endian, data)?;
+    let mut has_soname = false;
+    if let Some((dynamic, strings_index)) = sections.dynamic(endian, data)? {
+        let strings = sections.strings(endian, data, strings_index)?;
+        for entry in dynamic {
+            match entry.tag32(endian) {
+                Some(elf::DT_SONAME) => has_soname = true,
+                Some(elf::DT_RPATH | elf::DT_RUNPATH) => {
+                    let rpath = String::from_utf8_lossy(entry.string(endian, strings)?);
+                    if rpath
+                        .split(':')
+                        .any(|path| build_dirs.iter().any(|dir| path.starts_with(dir.as_str())))
+                    {
+                        issues.push(ElfIssue::BuildDirRpath(rpath.to_string()));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    // Static libraries are archives, not ELF files
+    if is_library && header.e_type(endian) == elf::ET_DYN && !has_soname {
+        issues.push(ElfIssue::MissingSoname);
+    }
+
+    Ok(issues)
+}
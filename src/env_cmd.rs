@@ -0,0 +1,14 @@
+#[derive(Args, Clone)]
+pub struct EnvCmd {
+    #[clap(help = "Path to the rinstall.yml configuration", from_global)]
+    pub config: Option<String>,
+    #[clap(long, value_enum, help = "The shell reading the exports")]
+    pub shell: EnvShell,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum EnvShell {
+    Bash,
+    Zsh,
+    Fish,
+}
@@ -0,0 +1,113 @@
+use std::env;
+
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::{Args, ValueEnum};
+use color_eyre::{eyre::Context, Result};
+use xdg::BaseDirectories;
+
+use crate::{dirs::Dirs, dirs_config_impl::DirsConfig};
+
+include!("env_cmd.rs");
+
+// The search path used by the desktop when XDG_DATA_DIRS is not set
+const DEFAULT_XDG_DATA_DIRS: &str = "/usr/local/share:/usr/share";
+
+impl EnvCmd {
+    pub fn run(self) -> Result<()> {
+        let dirs_config =
+            DirsConfig::load(self.config.as_deref(), false, &mut DirsConfig::default())?;
+        let dirs = Dirs::new(dirs_config, false).context("unable to create dirs")?;
+
+        for (var, dir) in missing_search_paths(&dirs)? {
+            match (self.shell, var) {
+                // fpath is the array tied to FPATH, which is usually not exported
+                (EnvShell::Zsh, "FPATH") => println!("fpath=(\"{dir}\" $fpath)"),
+                (EnvShell::Bash | EnvShell::Fish, "FPATH") => {}
+                (EnvShell::Bash | EnvShell::Zsh, "XDG_DATA_DIRS") => {
+                    println!("export {var}=\"{dir}:${{{var}:-{DEFAULT_XDG_DATA_DIRS}}}\"");
+                }
+                // An empty MANPATH entry stands for the default search path of man
+                (EnvShell::Bash | EnvShell::Zsh, _) => println!("export {var}=\"{dir}:${var}\""),
+                (EnvShell::Fish, "XDG_DATA_DIRS") => {
+                    println!(
+                        "set -q {var}; or set -gx --path {var} {}",
+                        DEFAULT_XDG_DATA_DIRS.replace(':', " ")
+                    );
+                    println!("set -gx --path {var} '{dir}' ${var}");
+                }
+                (EnvShell::Fish, _) => {
+                    if var == "MANPATH" {
+                        println!("set -q {var}; or set -gx {var} ''");
+                    }
+                    println!("set -gx {var} '{dir}' ${var}");
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn in_search_path(
+    search_path: &str,
+    dir: &Utf8Path,
+) -> bool {
+    search_path
+        .split(':')
+        .any(|path| Utf8Path::new(path) == dir)
+}
+
+// Remove the repeated and trailing slashes, i.e. ~/.local/share//man/
+fn normalize(dir: &Utf8Path) -> Utf8PathBuf {
+    dir.components().collect()
+}
+
+/// The directories of a user installation missing from the variables searched
+/// by the shell, man, the desktop and the zsh completion, along with the name of
+/// the variable
+pub fn missing_search_paths(dirs: &Dirs) -> Result<Vec<(&'static str, Utf8PathBuf)>> {
+    let mut missing = Vec::new();
+    if !in_search_path(&env::var("PATH").unwrap_or_default(), &dirs.bindir) {
+        missing.push(("PATH", normalize(&dirs.bindir)));
+    }
+
+    if let Some(mandir) = &dirs.mandir {
+        // man looks for the pages next to the executables in PATH, i.e. ~/.local/share/man
+        let next_to_bindir = dirs
+            .bindir
+            .parent()
+            .is_some_and(|parent| parent.join("share/man") == *mandir);
+        let found = match env::var("MANPATH") {
+            Ok(manpath) => {
+                in_search_path(&manpath, mandir)
+                    || next_to_bindir && manpath.split(':').any(str::is_empty)
+            }
+            Err(_) => next_to_bindir,
+        };
+        if !found {
+            missing.push(("MANPATH", normalize(mandir)));
+        }
+    }
+
+    // XDG_DATA_HOME is always searched first
+    let data_home = BaseDirectories::new()
+        .context("unable to initialize XDG Base Directories")?
+        .get_data_home();
+    let xdg_data_dirs = env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|xdg_data_dirs| !xdg_data_dirs.is_empty())
+        .unwrap_or_else(|| DEFAULT_XDG_DATA_DIRS.to_string());
+    if dirs.datarootdir.as_std_path() != data_home
+        && !in_search_path(&xdg_data_dirs, &dirs.datarootdir)
+    {
+        missing.push(("XDG_DATA_DIRS", normalize(&dirs.datarootdir)));
+    }
+
+    // zsh has no default directory for the completions of a single user
+    let zsh_completionsdir = dirs.datarootdir.join("zsh/site-functions");
+    if !in_search_path(&env::var("FPATH").unwrap_or_default(), &zsh_completionsdir) {
+        missing.push(("FPATH", normalize(&zsh_completionsdir)));
+    }
+
+    Ok(missing)
+}
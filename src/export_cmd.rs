@@ -0,0 +1,36 @@
+#[derive(Args, Clone)]
+pub struct ExportCmd {
+    #[clap(help = "Path to the rinstall.yml configuration", from_global)]
+    pub config: Option<String>,
+    #[clap(long = "system", help = "Export a package installed system-wide")]
+    pub system: bool,
+    #[clap(
+        long,
+        env,
+        requires = "system",
+        help = concat!("A prefix used in constructing the default values of the directories",
+                       " listed below. (system only)",
+                       " [default: /usr/local]")
+    )]
+    pub prefix: Option<String>,
+    #[clap(
+        long,
+        env,
+        help = concat!("The directory containing the rinstall pkginfo files",
+                       " [system default: @prefix@/var] [user default: @XDG_DATA_HOME@]")
+    )]
+    pub localstatedir: Option<String>,
+    #[clap(
+        long,
+        help = concat!("Name of the tarball to create (the suffix .tar.gz is added if not present)",
+                       " [default: <package name>]")
+    )]
+    pub tarball_name: Option<String>,
+    #[clap(
+        long,
+        help = "Name of the inner directory inside the tarball (default to the tarball-name)"
+    )]
+    pub directory_name: Option<String>,
+    #[clap(help = "The name or pkginfo file of the package to export")]
+    pub package: String,
+}
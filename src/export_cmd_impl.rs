@@ -0,0 +1,310 @@
+use std::fs;
+
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::Args;
+use color_eyre::{eyre::Context, Result};
+use colored::Colorize;
+use log::{info, warn};
+use serde_yaml::{Mapping, Value};
+
+use crate::{
+    dirs::Dirs,
+    dirs_config_impl::DirsConfig,
+    install_spec::RinstallVersion,
+    man_page::page_compression,
+    package_info::PackageInfo,
+    tarball::{append_data, write_tarball, TarballMetadata, METADATA_FILE},
+};
+
+include!("export_cmd.rs");
+
+// The categories of install.yml that can be reconstructed from an installed file
+#[derive(Clone, Copy)]
+enum Category {
+    Entry(&'static str),
+    // Entry installed by default in a subdirectory named after the package
+    PkgEntry(&'static str),
+    Man,
+    Completions(&'static str),
+    Icons,
+    Pixmaps,
+    Terminfo,
+}
+
+impl Category {
+    fn name(&self) -> &'static str {
+        match self {
+            Category::Entry(name) | Category::PkgEntry(name) => name,
+            Category::Man => "man",
+            Category::Completions(_) => "completions",
+            Category::Icons | Category::Pixmaps => "icons",
+            Category::Terminfo => "terminfo",
+        }
+    }
+}
+
+fn categories(dirs: &Dirs) -> Vec<(Category, Utf8PathBuf)> {
+    let datarootdir = &dirs.datarootdir;
+    [
+        (Category::Entry("exe"), Some(dirs.bindir.clone())),
+        (Category::Entry("admin-exe"), dirs.sbindir.clone()),
+        (Category::Entry("libs"), Some(dirs.libdir.clone())),
+        (Category::Entry("libexec"), Some(dirs.libexecdir.clone())),
+        (Category::Entry("includes"), dirs.includedir.clone()),
+        (
+            Category::Entry("pkg-config"),
+            Some(dirs.libdir.join("pkgconfig")),
+        ),
+        (Category::Entry("pam-modules"), dirs.pam_modulesdir.clone()),
+        (
+            Category::Entry("systemd-units"),
+            Some(dirs.systemd_unitsdir.join("system")),
+        ),
+        (
+            Category::Entry("systemd-user-units"),
+            Some(dirs.systemd_unitsdir.join("user")),
+        ),
+        (
+            Category::Entry("desktop-files"),
+            Some(datarootdir.join("applications")),
+        ),
+        (
+            Category::Entry("appstream-metadata"),
+            Some(datarootdir.join("metainfo")),
+        ),
+        (Category::Man, dirs.mandir.clone()),
+        (Category::PkgEntry("docs"), dirs.docdir.clone()),
+        (
+            Category::PkgEntry("licenses"),
+            Some(datarootdir.join("licenses")),
+        ),
+        (Category::PkgEntry("config"), Some(dirs.sysconfdir.clone())),
+        (Category::PkgEntry("data"), Some(dirs.datadir.clone())),
+        (
+            Category::Completions("bash"),
+            Some(datarootdir.join("bash-completion/completions")),
+        ),
+        (
+            Category::Completions("bash"),
+            Some(datarootdir.join("bash-completion")),
+        ),
+        (
+            Category::Completions("elvish"),
+            Some(datarootdir.join("elvish/lib")),
+        ),
+        (
+            Category::Completions("fish"),
+            Some(datarootdir.join("fish/vendor_completions.d")),
+        ),
+        (
+            Category::Completions("zsh"),
+            Some(datarootdir.join("zsh/site-functions")),
+        ),
+        (Category::Icons, Some(datarootdir.join("icons"))),
+        (Category::Pixmaps, Some(datarootdir.join("pixmaps"))),
+        (Category::Terminfo, Some(datarootdir.join("terminfo"))),
+    ]
+    .into_iter()
+    .filter_map(|(category, dir)| dir.map(|dir| (category, dir)))
+    .collect()
+}
+
+// Returns the entry for install.yml, or None if the file cannot be exported
+fn new_entry(
+    category: Category,
+    relative_path: &Utf8Path,
+    source: &Utf8Path,
+    pkg_name: &str,
+) -> Option<Value> {
+    let mut entry = Mapping::new();
+    entry.insert("src".into(), source.as_str().into());
+    match category {
+        Category::Entry(_) | Category::Completions(_) => {
+            entry.insert("dst".into(), relative_path.as_str().into());
+        }
+        Category::PkgEntry(_) => {
+            let destination = relative_path.strip_prefix(pkg_name).ok();
+            if destination.is_none() {
+                entry.insert("use-pkg-name".into(), false.into());
+            }
+            entry.insert(
+                "dst".into(),
+                destination.unwrap_or(relative_path).as_str().into(),
+            );
+        }
+        // The section and the initial are part of the installation directory
+        Category::Man | Category::Terminfo => {
+            entry.insert("dst".into(), relative_path.file_name()?.into());
+        }
+        // Icons are installed in icons/<theme>/<dimensions>/<type>/
+        Category::Icons => {
+            let mut components = relative_path.components();
+            for key in ["theme", "dimensions", "type"] {
+                entry.insert(key.into(), components.next()?.as_str().into());
+            }
+            let destination = components.as_path();
+            if destination.as_str().is_empty() {
+                return None;
+            }
+            entry.insert("dst".into(), destination.as_str().into());
+        }
+        Category::Pixmaps => {
+            entry.insert("dst".into(), relative_path.as_str().into());
+            entry.insert("pixmaps".into(), true.into());
+        }
+    }
+
+    Some(Value::Mapping(entry))
+}
+
+impl ExportCmd {
+    // Returns true if we need to use the system directories
+    pub fn system_dirs(&self) -> bool {
+        (unsafe { libc::getuid() } == 0) || self.system
+    }
+
+    pub fn run(self) -> Result<()> {
+        let mut opt_dirs = DirsConfig {
+            prefix: self.prefix.clone(),
+            localstatedir: self.localstatedir.clone(),
+            ..Default::default()
+        };
+        let dirs_config =
+            DirsConfig::load(self.config.as_deref(), self.system_dirs(), &mut opt_dirs)?;
+        let dirs = Dirs::new(dirs_config, self.system_dirs()).context("unable to create dirs")?;
+        let pkg_info = PackageInfo::load(&self.package, &dirs)?;
+        let pkg_name = &pkg_info.pkg_name;
+
+        // Map the files using the directories used when installing the package
+        let installed_dirs = if let Some(installed_dirs) = &pkg_info.dirs {
+            installed_dirs
+        } else {
+            warn!(
+                "pkginfo for {} does not contain the installation directories, using the current ones",
+                pkg_name.blue().italic()
+            );
+            &dirs
+        };
+        let categories = categories(installed_dirs);
+
+        let tarball_name = self.tarball_name.as_deref().unwrap_or(pkg_name);
+        let directory_name = Utf8Path::new(self.directory_name.as_deref().unwrap_or(tarball_name));
+        let filename = Utf8PathBuf::from(format!("{tarball_name}.tar.gz"));
+        info!(
+            "Exporting {} into {}",
+            pkg_name.italic().blue(),
+            filename.as_str().italic().yellow()
+        );
+
+        let mut archive = tar::Builder::new(Vec::new());
+        archive.follow_symlinks(false);
+        let mut package = Mapping::new();
+        let mut completions = Mapping::new();
+        let mut files = Vec::new();
+        for file in &pkg_info.files {
+            if !file.path.exists() {
+                warn!(
+                    "file {} does not exist anymore, skipping it",
+                    file.path.as_str().yellow().bold()
+                );
+                continue;
+            }
+            // Use the most specific directory containing the file
+            let Some((category, relative_path)) = categories
+                .iter()
+                .filter_map(|(category, dir)| {
+                    file.path
+                        .strip_prefix(dir)
+                        .ok()
+                        .map(|relative_path| (dir.as_str().len(), *category, relative_path))
+                })
+                .max_by_key(|(len, _, _)| *len)
+                .map(|(_, category, relative_path)| (category, relative_path))
+            else {
+                warn!(
+                    "file {} is not inside any known directory, skipping it",
+                    file.path.as_str().yellow().bold()
+                );
+                continue;
+            };
+
+            // Store the man pages uncompressed, installing them compresses them again if needed
+            let (relative_path, compression) = match category {
+                Category::Man => page_compression(relative_path.as_str())
+                    .map_or((relative_path, None), |(page, compression)| {
+                        (Utf8Path::new(page), Some(compression))
+                    }),
+                _ => (relative_path, None),
+            };
+            let source = match category {
+                Category::Completions(shell) => {
+                    Utf8Path::new("completions").join(shell).join(relative_path)
+                }
+                _ => Utf8Path::new(category.name()).join(relative_path),
+            };
+            let Some(entry) = new_entry(category, relative_path, &source, pkg_name) else {
+                warn!(
+                    "unable to export {} as {}, skipping it",
+                    file.path.as_str().yellow().bold(),
+                    category.name()
+                );
+                continue;
+            };
+            info!(
+                "Adding {} as {}",
+                file.path.as_str().purple().bold(),
+                source.as_str().cyan().bold()
+            );
+            let (entries, key) = match category {
+                Category::Completions(shell) => (&mut completions, shell),
+                _ => (&mut package, category.name()),
+            };
+            entries
+                .entry(key.into())
+                .or_insert_with(|| Value::Sequence(Vec::new()))
+                .as_sequence_mut()
+                .unwrap()
+                .push(entry);
+            files.push((&file.path, source, compression));
+        }
+        if !completions.is_empty() {
+            package.insert("completions".into(), Value::Mapping(completions));
+        }
+
+        let mut pkgs = Mapping::new();
+        pkgs.insert(pkg_name.as_str().into(), Value::Mapping(package));
+        let mut spec = Mapping::new();
+        spec.insert("rinstall".into(), RinstallVersion::LATEST.into());
+        spec.insert("pkgs".into(), Value::Mapping(pkgs));
+        let spec_file = serde_yaml::to_string(&spec).context("unable to serialize install.yml")?;
+
+        info!("Adding install.yml");
+        append_data(
+            &mut archive,
+            &directory_name.join("install.yml"),
+            spec_file.as_bytes(),
+        )?;
+        let metadata = serde_yaml::to_string(&TarballMetadata::new(Vec::new()))
+            .context("unable to serialize tarball metadata")?;
+        append_data(
+            &mut archive,
+            &directory_name.join(METADATA_FILE),
+            metadata.as_bytes(),
+        )?;
+        for (path, source, compression) in files {
+            if let Some(compression) = compression {
+                let data = fs::read(path).with_context(|| format!("unable to read {path}"))?;
+                let page = compression
+                    .decompress(&data)
+                    .with_context(|| format!("unable to decompress {path}"))?;
+                append_data(&mut archive, &directory_name.join(&source), &page)?;
+                continue;
+            }
+            archive
+                .append_path_with_name(path, directory_name.join(&source))
+                .with_context(|| format!("Unable to append path {path} to tarball"))?;
+        }
+
+        write_tarball(archive, &filename)
+    }
+}
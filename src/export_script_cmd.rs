@@ -0,0 +1,60 @@
+#[derive(ValueEnum, Clone, Copy)]
+pub enum ScriptFormat {
+    #[clap(help = "POSIX shell script, run as: ./install.sh [install|uninstall]")]
+    Sh,
+    #[clap(help = "GNU Makefile, with the install and uninstall targets")]
+    Make,
+}
+
+#[derive(Args, Clone)]
+pub struct ExportScriptCmd {
+    #[clap(
+        long,
+        help = concat!("Use the generated binaries and libraries from the",
+                       " debug profile (only effective for rust projects)")
+    )]
+    pub rust_debug_target: bool,
+    #[clap(
+        long,
+        help = concat!("Use the generated binaries and libraries from this",
+                       " cargo profile, `dev` uses the debug directory",
+                       " (only effective for rust projects)"),
+        conflicts_with = "rust_debug_target"
+    )]
+    pub rust_profile: Option<String>,
+    #[clap(
+        long,
+        help = concat!("Use the generated binaries and libraries from this",
+                       " target triple (only effective for rust projects)"),
+        env = "CARGO_BUILD_TARGET"
+    )]
+    pub rust_target_triple: Option<String>,
+    #[clap(
+        short = 'P',
+        long,
+        help = "Path to the directory containing the project to install",
+        default_value_os_t = std::env::current_dir()
+            .expect("unable to get current directory"),
+    )]
+    pub package_dir: std::path::PathBuf,
+    #[clap(
+        long,
+        help = concat!("Path to the build directory of meson, cmake and go projects,",
+                       " relative to the current directory")
+    )]
+    pub build_dir: Option<String>,
+    #[clap(
+        short = 'p',
+        long = "pkgs",
+        help = "List of packages to install, separated by a comma"
+    )]
+    pub packages: Vec<String>,
+    #[clap(long, value_enum, help = "Format of the installer")]
+    pub format: ScriptFormat,
+    #[clap(
+        short = 'o',
+        long,
+        help = "Path where the installer is written [default: the standard output]"
+    )]
+    pub output: Option<std::path::PathBuf>,
+}
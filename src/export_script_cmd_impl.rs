@@ -0,0 +1,420 @@
+use std::{collections::BTreeSet, fmt::Write as _, fs, os::unix::fs::PermissionsExt};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::{Args, ValueEnum};
+use color_eyre::{
+    eyre::{ensure, Context, ContextCompat},
+    Result,
+};
+use colored::Colorize;
+use log::info;
+use walkdir::WalkDir;
+
+use crate::{
+    dirs::Dirs,
+    dirs_config_impl::{DirsConfig, ManCompression},
+    install_spec::InstallSpec,
+    install_target::{EntryKind, InstallEntry},
+    man_page::ManFormat,
+    manifest::Manifest,
+    package::{CompletionsToInstall, Type},
+    project::{
+        set_build_directories, set_default_directories, set_rust_directories,
+        DefaultProjectDirectories, RustDirectories,
+    },
+};
+
+include!("export_script_cmd.rs");
+
+// The directories used in the installer, along with their placeholder used in templating
+const VARIABLES: [(&str, Option<&str>); 16] = [
+    ("prefix", Some("@prefix@")),
+    ("exec_prefix", Some("@exec_prefix@")),
+    ("bindir", Some("@bindir@")),
+    ("sbindir", None),
+    ("libdir", Some("@libdir@")),
+    ("libexecdir", None),
+    ("datarootdir", Some("@datarootdir@")),
+    ("datadir", Some("@datadir@")),
+    ("sysconfdir", Some("@sysconfdir@")),
+    ("localstatedir", Some("@localstatedir@")),
+    ("runstatedir", Some("@runstatedir@")),
+    ("includedir", Some("@includedir@")),
+    ("docdir", Some("@docdir@")),
+    ("mandir", Some("@mandir@")),
+    ("pam_modulesdir", Some("@pam_moduledirs@")),
+    ("systemd_unitsdir", Some("@systemd_unitsdir@")),
+];
+
+// Each directory contains the name of its variable, i.e. "@bindir@", which is
+// replaced by the variable reference when rendering the installer
+fn symbolic_dirs() -> Dirs {
+    let dir = |name: &str| Utf8PathBuf::from(format!("@{name}@"));
+    Dirs {
+        prefix: Some(dir("prefix")),
+        exec_prefix: Some(dir("exec_prefix")),
+        bindir: dir("bindir"),
+        sbindir: Some(dir("sbindir")),
+        libdir: dir("libdir"),
+        libexecdir: dir("libexecdir"),
+        datarootdir: dir("datarootdir"),
+        datadir: dir("datadir"),
+        sysconfdir: dir("sysconfdir"),
+        localstatedir: dir("localstatedir"),
+        runstatedir: dir("runstatedir"),
+        includedir: Some(dir("includedir")),
+        docdir: Some(dir("docdir")),
+        mandir: Some(dir("mandir")),
+        pam_modulesdir: Some(dir("pam_modulesdir")),
+        systemd_unitsdir: dir("systemd_unitsdir"),
+        man_compression: ManCompression::None,
+    }
+}
+
+// The default values of the variables, taken from the system configuration
+fn default_values() -> Vec<(&'static str, String)> {
+    let config = DirsConfig::system_config();
+    [
+        config.prefix,
+        config.exec_prefix,
+        config.bindir,
+        config.sbindir,
+        config.libdir,
+        config.libexecdir,
+        config.datarootdir,
+        config.datadir,
+        config.sysconfdir,
+        config.localstatedir,
+        config.runstatedir,
+        config.includedir,
+        config.docdir,
+        config.mandir,
+        config.pam_modulesdir,
+        config.systemd_unitsdir,
+    ]
+    .into_iter()
+    .zip(VARIABLES)
+    .map(|(value, (name, _))| (name, value.unwrap()))
+    .collect()
+}
+
+impl ScriptFormat {
+    fn var(
+        self,
+        name: &str,
+    ) -> String {
+        match self {
+            ScriptFormat::Sh => format!("${{{name}}}"),
+            ScriptFormat::Make => format!("$({name})"),
+        }
+    }
+
+    // Replace the leading placeholder, i.e. "@exec_prefix@bin/", with the variable reference
+    fn expand(
+        self,
+        path: &str,
+        escape: impl Fn(&str) -> String,
+    ) -> String {
+        let path = path.trim_end_matches('/');
+        match path.strip_prefix('@').and_then(|path| path.split_once('@')) {
+            Some((name, "")) => self.var(name),
+            Some((name, rest)) => format!(
+                "{}/{}",
+                self.var(name),
+                escape(rest.trim_start_matches('/'))
+            ),
+            None => escape(path),
+        }
+    }
+
+    // Escape a string placed inside double quotes
+    fn escape_double_quoted(
+        self,
+        s: &str,
+    ) -> String {
+        let mut res = String::new();
+        for c in s.chars() {
+            match c {
+                '$' if matches!(self, ScriptFormat::Make) => res.push_str("\\$$"),
+                '"' | '$' | '`' | '\\' => {
+                    res.push('\\');
+                    res.push(c);
+                }
+                _ => res.push(c),
+            }
+        }
+
+        res
+    }
+
+    /// The destination of a file, including the DESTDIR
+    fn destination(
+        self,
+        path: &Utf8Path,
+    ) -> String {
+        format!(
+            "\"{}{}\"",
+            self.var("DESTDIR"),
+            self.expand(path.as_str(), |s| self.escape_double_quoted(s))
+        )
+    }
+
+    /// The source of a file, relative to the project directory
+    fn source(
+        self,
+        path: &Utf8Path,
+    ) -> String {
+        let path = path.as_str().replace('\'', r"'\''");
+        match self {
+            ScriptFormat::Sh => format!("'{path}'"),
+            ScriptFormat::Make => format!("'{}'", path.replace('$', "$$")),
+        }
+    }
+
+    fn render(
+        self,
+        install: &[String],
+        uninstall: &[String],
+    ) -> String {
+        let mut res = String::new();
+        match self {
+            ScriptFormat::Sh => {
+                writeln!(res, "#!/bin/sh").unwrap();
+                writeln!(
+                    res,
+                    "# Generated by rinstall {}, do not edit",
+                    env!("CARGO_PKG_VERSION")
+                )
+                .unwrap();
+                writeln!(
+                    res,
+                    "# Usage: [DESTDIR=...] [prefix=...] $0 [install|uninstall]"
+                )
+                .unwrap();
+                writeln!(res, "set -e\n").unwrap();
+                writeln!(res, ": \"${{DESTDIR:=}}\"").unwrap();
+                for (name, value) in default_values() {
+                    writeln!(
+                        res,
+                        ": \"${{{name}:={}}}\"",
+                        self.expand(&value, |s| self.escape_double_quoted(s))
+                    )
+                    .unwrap();
+                }
+                for (function, commands) in [("do_install", install), ("do_uninstall", uninstall)] {
+                    writeln!(res, "\n{function}() {{\n    :").unwrap();
+                    for command in commands {
+                        writeln!(res, "    {command}").unwrap();
+                    }
+                    writeln!(res, "}}").unwrap();
+                }
+                res.push_str(concat!(
+                    "\ncase \"${1:-install}\" in\n",
+                    "    install) do_install ;;\n",
+                    "    uninstall) do_uninstall ;;\n",
+                    "    *)\n",
+                    "        echo \"usage: $0 [install|uninstall]\" >&2\n",
+                    "        exit 1\n",
+                    "        ;;\n",
+                    "esac\n"
+                ));
+            }
+            ScriptFormat::Make => {
+                writeln!(
+                    res,
+                    "# Generated by rinstall {}, do not edit",
+                    env!("CARGO_PKG_VERSION")
+                )
+                .unwrap();
+                writeln!(
+                    res,
+                    "# Usage: make [DESTDIR=...] [prefix=...] install|uninstall\n"
+                )
+                .unwrap();
+                writeln!(res, "DESTDIR =").unwrap();
+                for (name, value) in default_values() {
+                    writeln!(res, "{name} = {}", self.expand(&value, |s| s.to_string())).unwrap();
+                }
+                writeln!(res, "\n.PHONY: install uninstall").unwrap();
+                for (target, commands) in [("install", install), ("uninstall", uninstall)] {
+                    writeln!(res, "\n{target}:").unwrap();
+                    for command in commands {
+                        writeln!(res, "\t{command}").unwrap();
+                    }
+                }
+            }
+        }
+
+        res
+    }
+}
+
+// Returns the files to install for an entry, along with their destination
+fn entry_files(entry: &InstallEntry) -> Result<Vec<(Utf8PathBuf, Utf8PathBuf)>> {
+    entry.ensure_source_exists()?;
+    if entry.full_source.is_file() {
+        return Ok(vec![(
+            entry.full_source.clone(),
+            entry.destination_for_file(),
+        )]);
+    }
+
+    let mut files = Vec::new();
+    for file in WalkDir::new(&entry.full_source).sort_by_file_name() {
+        let file = file?;
+        if !file.file_type().is_file() {
+            continue;
+        }
+        let path = Utf8Path::from_path(file.path())
+            .with_context(|| format!("{:?} contains invalid UTF-8 characters", file.path()))?;
+        files.push((
+            path.to_path_buf(),
+            entry.destination_for_file_in_directory(path),
+        ));
+    }
+
+    Ok(files)
+}
+
+impl ExportScriptCmd {
+    pub fn run(self) -> Result<()> {
+        let packagedir = Utf8Path::from_path(&self.package_dir)
+            .with_context(|| format!("{:?} contains invalid UTF-8 characters", self.package_dir))?;
+        let install_spec = InstallSpec::new_from_path(packagedir)?;
+        let version = install_spec.version.clone();
+        let packages = install_spec.packages(&self.packages);
+
+        set_default_directories(DefaultProjectDirectories {
+            packagedir: Some(packagedir.to_owned()),
+        });
+        if packages.iter().any(|p| p.pkg_type == Type::Rust) {
+            set_rust_directories(RustDirectories::new(
+                Some(packagedir.to_owned()),
+                self.rust_debug_target,
+                self.rust_profile.as_deref(),
+                self.rust_target_triple.as_deref(),
+            )?);
+        }
+        set_build_directories(
+            &packages.iter().map(|p| p.pkg_type).collect::<Vec<_>>(),
+            packagedir,
+            self.build_dir.as_deref(),
+        );
+
+        let format = self.format;
+        let dirs = symbolic_dirs();
+        let mut install = Vec::new();
+        let mut uninstall = Vec::new();
+        let mut directories = BTreeSet::new();
+        let mut package_dirs = BTreeSet::new();
+        for package in packages {
+            let mut manifest = Manifest::new(package.name.as_ref().unwrap());
+            for entry in package.targets(&dirs, &version, true, &CompletionsToInstall::all())? {
+                ensure!(
+                    entry.generate.is_none(),
+                    "the completions {:?} need to be generated, which the generated installer cannot do",
+                    entry.source
+                );
+                ensure!(
+                    entry.kind != EntryKind::Man || ManFormat::from_path(&entry.source).is_none(),
+                    "the man page {:?} needs to be rendered, which the generated installer cannot do",
+                    entry.source
+                );
+                for (file, destination) in entry_files(&entry)? {
+                    // Keep the paths inside the project relative, so that the
+                    // installer can be moved along with the project
+                    let source = file.strip_prefix(packagedir).unwrap_or(&file);
+                    let executable = fs::metadata(&file)
+                        .with_context(|| format!("unable to read metadata of {file}"))?
+                        .permissions()
+                        .mode()
+                        & 0o111
+                        != 0;
+                    let mode = if executable { "755" } else { "644" };
+                    let destination_str = format.destination(&destination);
+
+                    let command = if entry.templating || entry.shebang.is_some() {
+                        let mut command = String::from("sed");
+                        if let Some(shebang) = &entry.shebang {
+                            let shebang = shebang
+                                .replace('\\', "\\\\")
+                                .replace('|', "\\|")
+                                .replace('&', "\\&");
+                            write!(
+                                command,
+                                " -e \"1s|^#!.*|#!{}|\"",
+                                format.escape_double_quoted(&shebang)
+                            )
+                            .unwrap();
+                        }
+                        // Without templating, only the placeholders of the shebang are replaced
+                        let address = if entry.templating { "" } else { "1" };
+                        for (name, placeholder) in VARIABLES {
+                            if let Some(placeholder) = placeholder {
+                                write!(
+                                    command,
+                                    " -e \"{address}s|{placeholder}|{}|g\"",
+                                    format.var(name)
+                                )
+                                .unwrap();
+                            }
+                        }
+                        if entry.shebang.is_some() {
+                            // The directories can end with a '/'
+                            command.push_str(" -e \"1s|//*|/|g\"");
+                        }
+                        format!(
+                            "{command} {} > {destination_str} && chmod {mode} {destination_str}",
+                            format.source(source)
+                        )
+                    } else {
+                        format!(
+                            "install -m {mode} {} {destination_str}",
+                            format.source(source)
+                        )
+                    };
+                    if entry.replace {
+                        install.push(command);
+                        uninstall.push(format!("rm -f {destination_str}"));
+                    } else {
+                        // Never overwrite nor remove the configuration files
+                        install.push(format!("test -e {destination_str} || {command}"));
+                    }
+
+                    directories.insert(destination.parent().unwrap().to_path_buf());
+                    manifest.add_file(&destination, &entry);
+                }
+            }
+            package_dirs.extend(manifest.package_dirs());
+        }
+
+        let install = directories
+            .iter()
+            .map(|dir| format!("mkdir -p {}", format.destination(dir)))
+            .chain(install)
+            .collect::<Vec<_>>();
+        // Remove the innermost directories first
+        uninstall.extend(
+            package_dirs
+                .iter()
+                .rev()
+                .map(|dir| format!("rmdir {} 2>/dev/null || true", format.destination(dir))),
+        );
+
+        let script = format.render(&install, &uninstall);
+        if let Some(output) = &self.output {
+            let output = Utf8Path::from_path(output)
+                .with_context(|| format!("{output:?} contains invalid UTF-8 characters"))?;
+            info!("Writing {}", output.as_str().cyan().bold());
+            fs::write(output, &script).with_context(|| format!("unable to write {output}"))?;
+            if matches!(format, ScriptFormat::Sh) {
+                fs::set_permissions(output, fs::Permissions::from_mode(0o755))
+                    .with_context(|| format!("unable to set permissions of {output}"))?;
+            }
+        } else {
+            print!("{script}");
+        }
+
+        Ok(())
+    }
+}
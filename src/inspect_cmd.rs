@@ -0,0 +1,32 @@
+#[derive(Args, Clone)]
+pub struct InspectCmd {
+    #[clap(help = "Path to the rinstall.yml configuration", from_global)]
+    pub config: Option<String>,
+    #[clap(
+        long = "system",
+        help = "Show the destinations of a system-wide installation"
+    )]
+    pub system: bool,
+    #[clap(
+        short = 'p',
+        long = "pkgs",
+        help = "List of packages to inspect, separated by a comma"
+    )]
+    pub packages: Vec<String>,
+    #[clap(
+        long,
+        help = concat!("Use the payload of this target triple, when the tarball",
+                       " contains multiple of them (only effective for rust projects)")
+    )]
+    pub rust_target_triple: Option<String>,
+    #[clap(
+        long,
+        help = concat!("Unpack the tarball into this directory, which can then be",
+                       " installed with `rinstall install -P <dir>`")
+    )]
+    pub extract: Option<std::path::PathBuf>,
+    #[clap(help = "Path to the tarball to inspect")]
+    pub tarball: std::path::PathBuf,
+    #[clap(flatten, next_help_heading = "DIRECTORIES")]
+    pub dirs: DirsConfig,
+}
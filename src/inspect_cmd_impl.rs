@@ -0,0 +1,174 @@
+use std::fs;
+
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+use clap::Args;
+use color_eyre::{
+    eyre::{ensure, Context, ContextCompat},
+    Result,
+};
+use colored::Colorize;
+use log::{info, warn};
+
+use crate::{
+    dirs::Dirs,
+    dirs_config_impl::DirsConfig,
+    package::{CompletionsToInstall, Type},
+    project::{set_rust_directories, RustDirectories},
+    tarball::{Tarball, METADATA_FILE},
+};
+
+include!("inspect_cmd.rs");
+
+impl InspectCmd {
+    // Returns true if we need to use the system directories
+    pub fn system_dirs(&self) -> bool {
+        (unsafe { libc::getuid() } == 0) || self.system
+    }
+
+    pub fn run(mut self) -> Result<()> {
+        let tarball_path = Utf8Path::from_path(&self.tarball)
+            .with_context(|| format!("{:?} contains invalid UTF-8 characters", self.tarball))?;
+        ensure!(tarball_path.exists(), "{tarball_path} does not exists");
+        let tarball = Tarball::open(tarball_path)?;
+        let rust_target_triple = tarball
+            .metadata
+            .select_rust_target_triple(self.rust_target_triple.as_deref())?;
+
+        let dirs_config =
+            DirsConfig::load(self.config.as_deref(), self.system_dirs(), &mut self.dirs)?;
+        let dirs = Dirs::new(dirs_config, self.system_dirs()).context("unable to create dirs")?;
+
+        let install_spec = tarball.metadata.parse_spec(tarball.spec_file.clone())?;
+        let version = install_spec.version.clone();
+        let packages = install_spec.packages(&self.packages);
+        if packages.iter().any(|p| p.pkg_type == Type::Rust) {
+            set_rust_directories(RustDirectories::new(None, false, None, rust_target_triple)?);
+        }
+
+        let payload = payload_files(&tarball, rust_target_triple)?;
+        for package in packages {
+            info!(
+                "{} {} {}",
+                ">>>".magenta(),
+                "Package".bright_black(),
+                package.name.as_ref().unwrap().italic().blue()
+            );
+            let install_entries = package.targets(
+                &dirs,
+                &version,
+                self.system_dirs(),
+                &CompletionsToInstall::all(),
+            )?;
+            for install_entry in &install_entries {
+                let files = payload
+                    .iter()
+                    .filter_map(|path| {
+                        install_entry
+                            .destination_for_tarball_path(path)
+                            .map(|destination| (path, destination))
+                    })
+                    .collect::<Vec<_>>();
+                if files.is_empty() {
+                    warn!(
+                        "{} is not contained in the tarball",
+                        install_entry.source.as_str().yellow().bold()
+                    );
+                }
+                for (path, destination) in files {
+                    info!(
+                        "{} -> {}",
+                        path.as_str().purple().bold(),
+                        destination.as_str().cyan().bold()
+                    );
+                }
+            }
+        }
+
+        if let Some(extract) = &self.extract {
+            let extract = Utf8Path::from_path(extract)
+                .with_context(|| format!("{extract:?} contains invalid UTF-8 characters"))?;
+            extract_tarball(&tarball, rust_target_triple, extract)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Returns the paths of all the files of the payload, relative to the project directory
+fn payload_files(
+    tarball: &Tarball,
+    rust_target_triple: Option<&str>,
+) -> Result<Vec<Utf8PathBuf>> {
+    let mut archive = tarball.archive();
+    let mut files = Vec::new();
+    for entry in archive
+        .entries()
+        .context("unable to create iterator over tarball archive")?
+    {
+        let entry = entry.context("invalid tarball archive")?;
+        if entry.header().entry_type().is_dir() {
+            continue;
+        }
+        let entry_path = entry
+            .path()
+            .context("unable to read path for tarball entry")?;
+        let path = Utf8Path::from_path(&entry_path)
+            .with_context(|| format!("invalid UTF8 path for tarball entry {:?}", entry_path))?;
+        if let Some(path) = tarball.payload_path(path, rust_target_triple) {
+            files.push(path);
+        }
+    }
+
+    Ok(files)
+}
+
+/// Unpack the spec file and the payload of the tarball into a directory
+fn extract_tarball(
+    tarball: &Tarball,
+    rust_target_triple: Option<&str>,
+    dir: &Utf8Path,
+) -> Result<()> {
+    info!("Extracting tarball into {}", dir.as_str().cyan().bold());
+    fs::create_dir_all(dir).with_context(|| format!("unable to create directory {dir:?}"))?;
+
+    let mut archive = tarball.archive();
+    for entry in archive
+        .entries()
+        .context("unable to create iterator over tarball archive")?
+    {
+        let mut entry = entry.context("invalid tarball archive")?;
+        if entry.header().entry_type().is_dir() {
+            continue;
+        }
+        let entry_path = entry
+            .path()
+            .context("unable to read path for tarball entry")?;
+        let path = Utf8Path::from_path(&entry_path)
+            .with_context(|| format!("invalid UTF8 path for tarball entry {:?}", entry_path))?;
+        let Some(path) = tarball.payload_path(path, rust_target_triple) else {
+            continue;
+        };
+        if path == METADATA_FILE {
+            continue;
+        }
+        ensure!(
+            path.components()
+                .all(|component| matches!(component, Utf8Component::Normal(_))),
+            "tarball entry {path} is not a valid relative path"
+        );
+
+        let destination = dir.join(&path);
+        fs::create_dir_all(destination.parent().unwrap())
+            .with_context(|| format!("unable to create directory {:?}", destination.parent()))?;
+        info!(
+            "Extracting {} -> {}",
+            path.as_str().purple().bold(),
+            destination.as_str().cyan().bold()
+        );
+        entry
+            .unpack(&destination)
+            .with_context(|| format!("unable to unpack {path} to {destination}"))?;
+    }
+
+    Ok(())
+}
@@ -2,6 +2,9 @@
 pub struct InstallCmd {
     #[clap(help = "Path to the rinstall.yml configuration", from_global)]
     pub config: Option<String>,
+    // Only use the default directories and the ones in dirs, i.e. when staging
+    #[clap(skip)]
+    pub skip_config: bool,
     #[clap(
         long = "system",
         help = "Perform a system-wide installation",
@@ -36,7 +39,16 @@ pub struct InstallCmd {
     #[clap(
         long,
         help = concat!("Use the generated binaries and libraries from this",
-                       " target triple (only effective for rust projects)")
+                       " cargo profile, `dev` uses the debug directory",
+                       " (only effective for rust projects)"),
+        conflicts_with = "rust_debug_target"
+    )]
+    pub rust_profile: Option<String>,
+    #[clap(
+        long,
+        help = concat!("Use the generated binaries and libraries from this",
+                       " target triple (only effective for rust projects)"),
+        env = "CARGO_BUILD_TARGET"
     )]
     pub rust_target_triple: Option<String>,
     #[clap(
@@ -60,6 +72,37 @@ pub struct InstallCmd {
             .expect("unable to get current directory"),
     )]
     pub package_dir: std::path::PathBuf,
+    #[clap(
+        long,
+        help = concat!("Path to the build directory of meson, cmake and go projects,",
+                       " relative to the current directory")
+    )]
+    pub build_dir: Option<String>,
+    #[clap(
+        long,
+        help = concat!("Build the project before installing it, running the build commands",
+                       " of the packages or `cargo build` for rust packages"),
+        conflicts_with = "tarball"
+    )]
+    pub build: bool,
+    #[clap(
+        long,
+        help = concat!("Remove the symbols and debug information from the ELF executables",
+                       " and libraries")
+    )]
+    pub strip: bool,
+    #[clap(
+        long,
+        help = concat!("Strip the ELF executables and libraries, writing their debug",
+                       " information in this directory, i.e. /usr/lib/debug")
+    )]
+    pub split_debug: Option<String>,
+    #[clap(
+        long,
+        help = concat!("Turn the warnings about the ELF executables and libraries into",
+                       " errors, i.e. a RPATH pointing into the build directory")
+    )]
+    pub deny_elf_warnings: bool,
     #[clap(
         short = 'p',
         long = "pkgs",
@@ -80,6 +123,14 @@ pub struct InstallCmd {
         requires = "destdir"
     )]
     pub packaging: bool,
+    #[clap(
+        long,
+        help = concat!("Write the list of installed files in the format used by",
+                       " a package manager, i.e. rpm=<path>. Possible formats are: rpm,",
+                       " deb-conffiles, deb-install, deb-dirs, arch-backup, spdx, cyclonedx"),
+        requires = "packaging"
+    )]
+    pub files_manifest: Vec<String>,
     #[clap(
         long,
         help = "List of the completion types to install. Possible values are: bash, elvish, fish, zsh"
@@ -92,6 +143,20 @@ pub struct InstallCmd {
         hide = true
     )]
     pub tarball: Option<std::path::PathBuf>,
+    #[clap(
+        long,
+        help = "Refuse to install the tarball unless it has been signed by this public key",
+        requires = "tarball",
+        hide = true
+    )]
+    pub verify_key: Option<std::path::PathBuf>,
+    #[clap(
+        long,
+        help = "Path to the detached signature of the tarball (default to <tarball>.sig)",
+        requires = "verify_key",
+        hide = true
+    )]
+    pub signature: Option<std::path::PathBuf>,
     #[clap(flatten, next_help_heading = "DIRECTORIES")]
     pub dirs: DirsConfig,
 }
@@ -1,6 +1,7 @@
 use std::{
     fs::{self, File},
-    io::{BufReader, Cursor, Read},
+    io::Read,
+    os::unix::fs::PermissionsExt,
 };
 
 use camino::{Utf8Path, Utf8PathBuf};
@@ -10,18 +11,27 @@ use color_eyre::{
     Result,
 };
 use colored::Colorize;
-use flate2::bufread::GzDecoder;
 use log::{info, warn};
 use walkdir::WalkDir;
 
 use crate::{
     dirs::Dirs,
-    dirs_config_impl::DirsConfig,
+    dirs_config_impl::{DirsConfig, ManCompression},
+    elf_check::{check_elf, expected_machine, ElfIssue},
+    env_cmd_impl::missing_search_paths,
     install_spec::InstallSpec,
-    install_target::InstallEntry,
+    install_target::{EntryKind, InstallEntry},
+    man_page::{page_name, ManFormat},
+    manifest::{FilesManifest, Manifest, ManifestFile},
     package::{CompletionsToInstall, Package, Type},
     package_info::PackageInfo,
-    project::{RustDirectories, RUST_DIRECTORIES, RUST_DIRECTORIES_ONCE},
+    project::{
+        run_build_command, run_generate_command, set_build_directories, set_default_directories,
+        set_rust_directories, DefaultProjectDirectories, RustDirectories,
+    },
+    signature::{signature_path, PublicKey},
+    strip,
+    tarball::Tarball,
     templating::apply_templating,
     utils::{append_destdir, write_to_file},
     Uninstall,
@@ -39,8 +49,39 @@ impl InstallCmd {
         self.skip_pkg_info || self.packaging
     }
     pub fn run(mut self) -> Result<()> {
-        let dirs_config =
-            DirsConfig::load(self.config.as_deref(), self.system_dirs(), &mut self.dirs)?;
+        let files_manifests = self
+            .files_manifest
+            .iter()
+            .map(|files_manifest| FilesManifest::parse(files_manifest))
+            .collect::<Result<Vec<_>>>()?;
+
+        let manifests = self.install()?;
+
+        for files_manifest in &files_manifests {
+            if self.accept_changes {
+                info!(
+                    "Writing files manifest {}",
+                    files_manifest.path.as_str().cyan().bold()
+                );
+                files_manifest.write(&manifests, self.destdir.as_deref())?;
+            } else {
+                info!(
+                    "Would write files manifest {}",
+                    files_manifest.path.as_str().cyan().bold()
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Install the selected packages and return the files installed by each of them
+    pub fn install(&mut self) -> Result<Vec<Manifest>> {
+        let dirs_config = if self.skip_config {
+            DirsConfig::load_defaults(self.system_dirs(), &mut self.dirs)?
+        } else {
+            DirsConfig::load(self.config.as_deref(), self.system_dirs(), &mut self.dirs)?
+        };
         let dirs = Dirs::new(dirs_config, self.system_dirs()).context("unable to create dirs")?;
 
         let completions = if let Some(completions) = self.completions.as_ref() {
@@ -48,6 +89,7 @@ impl InstallCmd {
         } else {
             CompletionsToInstall::all()
         };
+        let mut manifests = Vec::new();
 
         // Disable the experimental tarball feature
         if let Some(tarball) = self.tarball.as_ref() {
@@ -55,66 +97,49 @@ impl InstallCmd {
                 .with_context(|| format!("{tarball:?} contains invalid UTF-8 characters"))?;
             ensure!(tarball.exists(), "{tarball} does not exists");
 
-            // Decoompress the content of the archive
-            let mut decoder = GzDecoder::new(BufReader::new(
-                File::open(tarball).with_context(|| format!("unable to open tarball {tarball}"))?,
-            ));
-            let mut buf = Vec::new();
-            decoder
-                .read_to_end(&mut buf)
-                .with_context(|| format!("unable to decompress tarball {tarball}"))?;
-
-            let cursor = Cursor::new(buf);
-            let mut archive = tar::Archive::new(cursor);
-
-            let mut tarball_entries = archive
-                .entries()
-                .context("unable to create iterator over tarball archive")?;
-            let mut entry = tarball_entries
-                .next()
-                .context("empty tarball archive")?
-                .context("invalid tarball archive")?;
-            ensure!(
-                entry
-                    .path()
-                    .context("invalid path in tarball archive")?
-                    .file_name()
-                    .context("invalid path in tarball archive")?
-                    .to_string_lossy()
-                    == "install.yml",
-                "the first file is not rinstall spec file"
-            );
-            let mut spec_file = String::new();
-            // Pass the error up with the ? operator
-            entry
-                .read_to_string(&mut spec_file)
-                .context("unable to read spec file from tarball")?;
-            let install_spec = InstallSpec::new_from_string(spec_file)?;
+            if let Some(verify_key) = &self.verify_key {
+                let verify_key = Utf8Path::from_path(verify_key)
+                    .with_context(|| format!("{verify_key:?} contains invalid UTF-8 characters"))?;
+                let signature = if let Some(signature) = &self.signature {
+                    Utf8Path::from_path(signature)
+                        .with_context(|| {
+                            format!("{signature:?} contains invalid UTF-8 characters")
+                        })?
+                        .to_path_buf()
+                } else {
+                    signature_path(tarball)
+                };
+                PublicKey::load(verify_key)?
+                    .verify_file(tarball, &signature)
+                    .with_context(|| format!("unable to verify tarball {tarball}"))?;
+                info!(
+                    "Verified signature {}",
+                    signature.as_str().italic().yellow()
+                );
+            }
+
+            let tarball = Tarball::open(tarball)?;
+            let rust_target_triple = tarball
+                .metadata
+                .select_rust_target_triple(self.rust_target_triple.as_deref())?;
+            let install_spec = tarball.metadata.parse_spec(tarball.spec_file.clone())?;
             let version = install_spec.version.clone();
 
             let packages = install_spec.packages(&self.packages);
             // TODO
             // Initialize project directories (only rust for now)
             if packages.iter().any(|p| p.pkg_type == Type::Rust) {
-                RUST_DIRECTORIES_ONCE.call_once(|| {
-                    // We use call_once on std::once::Once, this is safe
-                    unsafe {
-                        RUST_DIRECTORIES = Some(
-                            RustDirectories::new(
-                            None,
-                            self.rust_debug_target,
-                            self.rust_target_triple.as_deref(),
-                        )
-                        // TODO
-                        .unwrap(),
-                        );
-                    }
-                });
+                set_rust_directories(RustDirectories::new(
+                    None,
+                    self.rust_debug_target,
+                    self.rust_profile.as_deref(),
+                    rust_target_triple,
+                )?);
             }
 
             for package in packages {
-                let mut pkg_installer = PackageInstaller::new(&package, &self, &dirs)
-                    .with_context(|| {
+                let mut pkg_installer =
+                    PackageInstaller::new(&package, self, &dirs).with_context(|| {
                         format!(
                             "failed to create package installer for package {:?}",
                             package.name
@@ -123,42 +148,72 @@ impl InstallCmd {
                 let install_entries =
                     package.targets(&dirs, &version, self.system_dirs(), &completions)?;
 
-                while let Some(Ok(mut tarball_entry)) = tarball_entries.next() {
+                let mut archive = tarball.archive();
+                let tarball_entries = archive
+                    .entries()
+                    .context("unable to create iterator over tarball archive")?;
+                for tarball_entry in tarball_entries {
+                    let mut tarball_entry = tarball_entry.context("invalid tarball archive")?;
+                    // Directories are created when unpacking the files they contain
+                    if tarball_entry.header().entry_type().is_dir() {
+                        continue;
+                    }
                     let entry_path = tarball_entry
                         .path()
                         .context("unable to read path for tarball entry")?;
-                    let path = Utf8Path::from_path(&entry_path)
-                        .with_context(|| {
-                            format!("invalid UTF8 path for tarball entry {:?}", entry_path)
-                        })?
-                        .to_path_buf();
-
-                    // Is equal to ["rinstall-0.3.0/rinstall", "rinstall-0.3.0", ""]
-                    let ancestors = path.ancestors().collect::<Vec<_>>();
-                    // Get the second last
-                    let prefix = ancestors[ancestors.len() - 2];
-                    // and then strip it from the path, as it's just the directory
-                    let path = path.strip_prefix(prefix).unwrap();
+                    let path = Utf8Path::from_path(&entry_path).with_context(|| {
+                        format!("invalid UTF8 path for tarball entry {:?}", entry_path)
+                    })?;
+                    // Only take the payload of the selected target triple and
+                    // skip the ones of the others
+                    let Some(path) = tarball.payload_path(path, rust_target_triple) else {
+                        continue;
+                    };
 
                     // Skip entries in the tarball that are not inside the rinstall spec file
                     // This is okay because the tarball entries list does not match the target list
                     // i.e. a directory in the spec file will have all the corresponding files in the entries
                     for install_entry in &install_entries {
-                        let destination = if install_entry.source == path {
-                            Some(install_entry.destination_for_file())
-                        } else if let Ok(strip_prefix) = path.strip_prefix(&install_entry.source) {
-                            Some(install_entry.destination.join(strip_prefix))
-                        } else {
-                            None
-                        };
-                        if let Some(destination) = destination {
-                            let installer = |destination| -> Result<()> {
-                                // unpack_in returns true if the entry was unpacked
-                                // false if the entry contains '..' and thus was not packed
-                                match tarball_entry.unpack_in(destination)? {
-                                    true => Err(color_eyre::eyre::anyhow!("path contains '..'")),
-                                    false => Ok(()),
+                        if let Some(destination) = install_entry.destination_for_tarball_path(&path)
+                        {
+                            let installer = |destination: Utf8PathBuf| -> Result<()> {
+                                if !tarball_entry.header().entry_type().is_file() {
+                                    return tarball_entry
+                                        .unpack(&destination)
+                                        .map(|_| ())
+                                        .with_context(|| {
+                                            format!("unable to unpack {path} to {destination}")
+                                        });
                                 }
+                                // Check the payload before writing it, an invalid file must not
+                                // replace the installed one
+                                let mut data = Vec::new();
+                                tarball_entry
+                                    .read_to_end(&mut data)
+                                    .with_context(|| format!("unable to read {path}"))?;
+                                self.check_elf(install_entry, &data, rust_target_triple)
+                                    .with_context(|| format!("invalid file {path}"))?;
+                                let mode = tarball_entry
+                                    .header()
+                                    .mode()
+                                    .with_context(|| format!("invalid mode for {path}"))?;
+                                // Replace the file instead of truncating it, as unpack does,
+                                // i.e. when it is a running executable
+                                if destination.exists() {
+                                    fs::remove_file(&destination).with_context(|| {
+                                        format!("unable to remove file {destination}")
+                                    })?;
+                                }
+                                fs::write(&destination, &data).with_context(|| {
+                                    format!("unable to write file {destination}")
+                                })?;
+                                fs::set_permissions(
+                                    &destination,
+                                    fs::Permissions::from_mode(mode & 0o777),
+                                )
+                                .with_context(|| {
+                                    format!("unable to set the permissions of {destination}")
+                                })
                             };
                             pkg_installer.install_target(
                                 install_entry,
@@ -166,10 +221,13 @@ impl InstallCmd {
                                 self.accept_changes,
                                 installer,
                             )?;
-                            continue;
+                            break;
                         }
                     }
                 }
+
+                pkg_installer.install_pkg_info()?;
+                manifests.push(pkg_installer.manifest);
             }
         } else {
             let packagedir = Utf8Path::from_path(&self.package_dir).with_context(|| {
@@ -180,35 +238,53 @@ impl InstallCmd {
 
             let packages = install_spec.packages(&self.packages);
 
+            if self.build {
+                self.build_project(packagedir, &packages)?;
+            }
+
+            set_default_directories(DefaultProjectDirectories {
+                packagedir: Some(packagedir.to_owned()),
+            });
             if packages.iter().any(|p| p.pkg_type == Type::Rust) {
-                RUST_DIRECTORIES_ONCE.call_once(|| {
-                    // We use call_once on std::once::Once, this is safe
-                    unsafe {
-                        RUST_DIRECTORIES = Some(
-                            RustDirectories::new(
-                            Some(packagedir.to_owned()),
-                            self.rust_debug_target,
-                            self.rust_target_triple.as_deref(),
-                        )
-                        // TODO                       
-                        .unwrap(),
-                        );
-                    }
-                });
+                set_rust_directories(RustDirectories::new(
+                    Some(packagedir.to_owned()),
+                    self.rust_debug_target,
+                    self.rust_profile.as_deref(),
+                    self.rust_target_triple.as_deref(),
+                )?);
             }
+            set_build_directories(
+                &packages.iter().map(|p| p.pkg_type).collect::<Vec<_>>(),
+                packagedir,
+                self.build_dir.as_deref(),
+            );
 
             for package in packages {
-                let mut pkg_installer = PackageInstaller::new(&package, &self, &dirs)?;
+                let mut pkg_installer = PackageInstaller::new(&package, self, &dirs)?;
 
                 let entries = package.targets(&dirs, &version, self.system_dirs(), &completions)?;
                 for install_entry in entries {
-                    ensure!(
-                        install_entry.full_source.exists(),
-                        "File {:?} does not exist",
-                        install_entry.source
-                    );
+                    install_entry.ensure_source_exists()?;
 
-                    if install_entry.full_source.is_file() {
+                    if let Some(command) = &install_entry.generate {
+                        let installer = |destination: Utf8PathBuf| -> Result<()> {
+                            let output = run_generate_command(packagedir, command)?;
+                            fs::write(&destination, output)
+                                .with_context(|| format!("unable to write file {destination}"))
+                        };
+
+                        pkg_installer
+                            .install_target(
+                                &install_entry,
+                                install_entry.destination.clone(),
+                                self.accept_changes,
+                                installer,
+                            )
+                            .with_context(|| {
+                                format!("failed to install {:?}", install_entry.source)
+                            })?;
+                    } else if install_entry.full_source.is_file() {
+                        self.check_elf_file(&install_entry, &install_entry.full_source)?;
                         let destination = install_entry.destination_for_file();
                         let installer = |destination| -> Result<()> {
                             std::fs::copy(&install_entry.full_source, &destination)
@@ -241,15 +317,15 @@ impl InstallCmd {
                                     return Ok(());
                                 }
                                 let full_file_path = Utf8Path::from_path(entry.path()).unwrap();
+                                self.check_elf_file(&install_entry, full_file_path)?;
 
                                 let destination =
                                     install_entry.destination_for_file_in_directory(full_file_path);
                                 let installer = |destination| -> Result<()> {
-                                    std::fs::copy(&install_entry.full_source, &destination)
+                                    std::fs::copy(full_file_path, &destination)
                                         .with_context(|| {
                                             format!(
-                                                "unable to copy file {} to {destination}",
-                                                install_entry.full_source
+                                                "unable to copy file {full_file_path} to {destination}"
                                             )
                                         })
                                         .map(|_| ())
@@ -280,11 +356,144 @@ impl InstallCmd {
                 }
 
                 pkg_installer.install_pkg_info()?;
+                manifests.push(pkg_installer.manifest);
+            }
+        }
+
+        // The shell might not find the files installed in the user directories
+        if !self.system_dirs() && self.destdir.is_none() && self.accept_changes {
+            for (var, dir) in missing_search_paths(&dirs)? {
+                if manifests
+                    .iter()
+                    .flat_map(|manifest| &manifest.files)
+                    .any(|file| file.path.starts_with(&dir))
+                {
+                    // Only zsh reads FPATH
+                    let shell = if var == "FPATH" {
+                        "zsh"
+                    } else {
+                        "<bash|zsh|fish>"
+                    };
+                    warn!(
+                        "{} is not in {var}, run `{}` to print the exports needed",
+                        dir.as_str().yellow().bold(),
+                        format!("rinstall env --shell {shell}").bold()
+                    );
+                }
+            }
+        }
+
+        Ok(manifests)
+    }
+
+    // Same as check_elf, for a file in the project directory
+    fn check_elf_file(
+        &self,
+        entry: &InstallEntry,
+        file: &Utf8Path,
+    ) -> Result<()> {
+        if !matches!(
+            entry.kind,
+            EntryKind::Executable | EntryKind::Library | EntryKind::PamModule
+        ) {
+            return Ok(());
+        }
+
+        let data = fs::read(file).with_context(|| format!("unable to read file {file}"))?;
+        self.check_elf(entry, &data, self.rust_target_triple.as_deref())
+    }
+
+    // Check the executables and libraries before installing them, the wrong
+    // machine is an error when installing into this system
+    fn check_elf(
+        &self,
+        entry: &InstallEntry,
+        data: &[u8],
+        rust_target_triple: Option<&str>,
+    ) -> Result<()> {
+        if !matches!(
+            entry.kind,
+            EntryKind::Executable | EntryKind::Library | EntryKind::PamModule
+        ) {
+            return Ok(());
+        }
+
+        // The build directories are unknown when installing from a tarball
+        // The paths in RPATH are absolute, i.e. not relative to the current directory
+        let build_dirs = if self.tarball.is_some() {
+            Vec::new()
+        } else {
+            Utf8Path::from_path(&self.package_dir)
+                .into_iter()
+                .chain(entry.pkg_type.builddir())
+                .filter_map(|dir| dir.canonicalize_utf8().ok())
+                .collect::<Vec<_>>()
+        };
+        let build_dirs = build_dirs
+            .iter()
+            .map(|dir| dir.as_path())
+            .collect::<Vec<_>>();
+        let issues = check_elf(
+            data,
+            entry.kind == EntryKind::Library,
+            expected_machine(rust_target_triple),
+            &build_dirs,
+        )
+        .with_context(|| format!("unable to check {}", entry.source))?;
+        for issue in issues {
+            let is_error = self.deny_elf_warnings
+                || matches!(issue, ElfIssue::WrongMachine { .. }) && self.destdir.is_none();
+            ensure!(!is_error, "{}: {issue}", entry.source);
+            warn!("{}: {issue}", entry.source.as_str().yellow().bold());
+        }
+
+        Ok(())
+    }
+
+    // Run the build commands of the packages, each command is run once even
+    // when it is shared by multiple packages
+    fn build_project(
+        &self,
+        packagedir: &Utf8Path,
+        packages: &[Package],
+    ) -> Result<()> {
+        let mut commands: Vec<String> = Vec::new();
+        for package in packages {
+            let package_commands = if !package.build.is_empty() {
+                package.build.clone()
+            } else if package.pkg_type == Type::Rust {
+                vec![self.cargo_build_command()]
+            } else {
+                Vec::new()
+            };
+            for command in package_commands {
+                if !commands.contains(&command) {
+                    commands.push(command);
+                }
             }
         }
 
+        for command in commands {
+            run_build_command(packagedir, &command)?;
+        }
+
         Ok(())
     }
+
+    // The cargo invocation generating the binaries used by RustDirectories
+    fn cargo_build_command(&self) -> String {
+        let mut command = String::from("cargo build");
+        if let Some(rust_profile) = &self.rust_profile {
+            command.push_str(&format!(" --profile {rust_profile}"));
+        } else if !self.rust_debug_target {
+            command.push_str(" --release");
+        }
+        if let Some(rust_target_triple) = &self.rust_target_triple {
+            command.push_str(&format!(" --target {rust_target_triple}"));
+        }
+
+        command
+    }
 }
 
 struct PackageInstaller<'a> {
@@ -292,6 +501,7 @@ struct PackageInstaller<'a> {
     dirs: &'a Dirs,
     install_opts: &'a InstallCmd,
     pkg_info: PackageInfo,
+    manifest: Manifest,
 }
 
 impl<'a> PackageInstaller<'a> {
@@ -300,7 +510,12 @@ impl<'a> PackageInstaller<'a> {
         install_opts: &'a InstallCmd,
         dirs: &'a Dirs,
     ) -> Result<Self> {
-        let pkg_info = PackageInfo::new(package.name.as_ref().unwrap(), dirs);
+        let pkg_info = PackageInfo {
+            version: package.version.clone(),
+            homepage: package.homepage.clone(),
+            license: package.license.clone(),
+            ..PackageInfo::new(package.name.as_ref().unwrap(), dirs)
+        };
         let pkg_info_path = append_destdir(&pkg_info.path, install_opts.destdir.as_deref());
         let pkg_already_installed = pkg_info_path.exists();
         info!(
@@ -342,6 +557,12 @@ impl<'a> PackageInstaller<'a> {
             check_for_overwrite: pkg_already_installed,
             dirs,
             install_opts,
+            manifest: Manifest {
+                version: package.version.clone(),
+                homepage: package.homepage.clone(),
+                license: package.license.clone(),
+                ..Manifest::new(&pkg_info.pkg_name)
+            },
             pkg_info,
         })
     }
@@ -380,10 +601,15 @@ impl<'a> PackageInstaller<'a> {
 
             installer(destination_destdir.clone())?;
 
-            self.apply_templating(target)?;
+            self.apply_templating(target, &destination_destdir)?;
+            self.rewrite_shebang(target, &destination_destdir)?;
+            self.render_man(target, &destination_destdir)?;
+            self.compress_man(target, &destination_destdir)?;
+            self.strip(target, &destination_destdir)?;
 
             self.pkg_info
                 .add_file(&destination, &destination_destdir, target.replace)?;
+            self.manifest.add_file(&destination, target);
         } else {
             info!(
                 "Would install {} -> {}",
@@ -398,21 +624,183 @@ impl<'a> PackageInstaller<'a> {
     fn apply_templating(
         &self,
         entry: &InstallEntry,
+        destination: &Utf8Path,
     ) -> Result<()> {
         if entry.templating {
-            let mut file = File::open(&entry.destination)
-                .with_context(|| format!("unable to open file {:?}", entry.destination))?;
+            let mut file = File::open(destination)
+                .with_context(|| format!("unable to open file {:?}", destination))?;
             let mut file_contents = Vec::new();
             file.read_to_end(&mut file_contents)
-                .with_context(|| format!("unable to read file {:?}", entry.destination))?;
+                .with_context(|| format!("unable to read file {:?}", destination))?;
             let contents = apply_templating(&file_contents, self.dirs)
                 .with_context(|| format!("unable to apply templating to {:?}", entry.source))?;
-            write_to_file(&entry.destination, contents.as_bytes())?;
+            write_to_file(destination, contents.as_bytes())?;
         }
 
         Ok(())
     }
 
+    // Replace the interpreter of the installed script, the placeholders of the
+    // new interpreter are replaced as in templating, i.e. "@bindir@/python3"
+    fn rewrite_shebang(
+        &self,
+        entry: &InstallEntry,
+        destination: &Utf8Path,
+    ) -> Result<()> {
+        let Some(shebang) = &entry.shebang else {
+            return Ok(());
+        };
+
+        let data =
+            fs::read(destination).with_context(|| format!("unable to read file {destination}"))?;
+        let Some(script) = data.strip_prefix(b"#!") else {
+            warn!(
+                "{} does not start with a shebang, it has been left untouched",
+                entry.source.as_str().yellow().bold()
+            );
+            return Ok(());
+        };
+        let shebang = apply_templating(shebang.as_bytes(), self.dirs).with_context(|| {
+            format!(
+                "unable to apply templating to the shebang of {:?}",
+                entry.source
+            )
+        })?;
+        // The directories can end with a '/', i.e. "@bindir@/python3"; only the repeated
+        // slashes of the interpreter are removed, the arguments are kept as they are
+        let shebang = shebang.trim_start();
+        let (interpreter, args) =
+            shebang.split_at(shebang.find(char::is_whitespace).unwrap_or(shebang.len()));
+        let interpreter = Utf8Path::new(interpreter)
+            .components()
+            .collect::<Utf8PathBuf>();
+        let shebang = format!("{interpreter}{args}");
+        // Keep everything after the first line as it is
+        let rest = script
+            .iter()
+            .position(|c| *c == b'\n')
+            .map_or(&[][..], |pos| &script[pos..]);
+        let mut contents = format!("#!{shebang}").into_bytes();
+        contents.extend_from_slice(rest);
+        write_to_file(destination, &contents)?;
+
+        Ok(())
+    }
+
+    // Render the installed man page to roff when it has been written in scdoc or
+    // Markdown, the tarballs already contain the rendered man pages
+    fn render_man(
+        &self,
+        entry: &InstallEntry,
+        destination: &Utf8Path,
+    ) -> Result<()> {
+        let Some(format) =
+            ManFormat::from_path(&entry.source).filter(|_| entry.kind == EntryKind::Man)
+        else {
+            return Ok(());
+        };
+
+        let source = fs::read_to_string(destination)
+            .with_context(|| format!("unable to read file {destination}"))?;
+        let destination_for_file = entry.destination_for_file();
+        let contents = format
+            .render(
+                &source,
+                page_name(destination_for_file.file_name().unwrap()),
+            )
+            .with_context(|| format!("unable to render man page {:?}", entry.source))?;
+        write_to_file(destination, contents.as_bytes())?;
+
+        Ok(())
+    }
+
+    // Compress the installed man page, its destination already has the extension
+    fn compress_man(
+        &self,
+        entry: &InstallEntry,
+        destination: &Utf8Path,
+    ) -> Result<()> {
+        if entry.kind != EntryKind::Man || self.dirs.man_compression == ManCompression::None {
+            return Ok(());
+        }
+
+        let data =
+            fs::read(destination).with_context(|| format!("unable to read file {destination}"))?;
+        let compressed = self
+            .dirs
+            .man_compression
+            .compress(&data)
+            .with_context(|| format!("unable to compress {destination}"))?;
+        write_to_file(destination, &compressed)?;
+
+        Ok(())
+    }
+
+    // Strip the installed file and write its debug file, when requested
+    fn strip(
+        &mut self,
+        entry: &InstallEntry,
+        destination: &Utf8Path,
+    ) -> Result<()> {
+        let split_debug = self.install_opts.split_debug.as_deref();
+        let strip = entry.strip
+            || matches!(entry.kind, EntryKind::Executable | EntryKind::Library)
+                && (self.install_opts.strip || split_debug.is_some());
+        if !strip {
+            return Ok(());
+        }
+
+        let data =
+            fs::read(destination).with_context(|| format!("unable to read file {destination}"))?;
+        // The files that are not ELF, i.e. scripts, are left untouched
+        let Some(stripped) = strip::strip(&data, split_debug.is_some())
+            .with_context(|| format!("unable to strip {destination}"))?
+        else {
+            return Ok(());
+        };
+        write_to_file(destination, &stripped.data)?;
+
+        let (Some(split_debug), Some(debug)) = (split_debug, stripped.debug) else {
+            return Ok(());
+        };
+        let Some(build_id) = stripped.build_id.filter(|build_id| build_id.len() > 2) else {
+            warn!(
+                "{} has no build-id, its debug information has been discarded",
+                entry.source.as_str().yellow().bold()
+            );
+            return Ok(());
+        };
+        // The layout used by the debuggers to find the debug files
+        let debug_file = Utf8Path::new(split_debug)
+            .join(".build-id")
+            .join(&build_id[..2])
+            .join(format!("{}.debug", &build_id[2..]));
+        let debug_file_destdir = append_destdir(&debug_file, self.install_opts.destdir.as_deref());
+        info!(
+            "Installing {} -> {}",
+            "debug information".purple().bold(),
+            debug_file_destdir.as_str().cyan().bold()
+        );
+        fs::create_dir_all(debug_file_destdir.parent().unwrap()).with_context(|| {
+            format!(
+                "unable to create directory {:?}",
+                debug_file_destdir.parent()
+            )
+        })?;
+        fs::write(&debug_file_destdir, debug)
+            .with_context(|| format!("unable to write file {debug_file_destdir}"))?;
+        // Uninstall it along with the package, packagers ship it separately
+        self.pkg_info
+            .add_file(&debug_file, &debug_file_destdir, true)?;
+        self.manifest.files.push(ManifestFile {
+            path: debug_file,
+            kind: EntryKind::Default,
+            replace: true,
+        });
+
+        Ok(())
+    }
+
     /// Returns true if the file already exists and we should skip installation
     fn handle_existing_file(
         &self,
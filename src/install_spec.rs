@@ -1,14 +1,19 @@
 use std::{collections::HashMap, fs};
 
-use camino::Utf8Path;
+use camino::{Utf8Path, Utf8PathBuf};
 use color_eyre::{
-    eyre::{bail, Context},
+    eyre::{bail, Context, ContextCompat},
     Result,
 };
 use semver::Version;
 use serde::Deserialize;
+use serde_yaml::{Mapping, Value};
 
-use crate::Package;
+use crate::{
+    package::{discover_sources, AUTO_ENTRIES},
+    project::{workspace_root, RustDirectories},
+    Package,
+};
 
 #[derive(Deserialize, Clone)]
 pub enum RinstallVersion {
@@ -16,6 +21,42 @@ pub enum RinstallVersion {
     V0_1_0,
     #[serde(rename = "0.2.0")]
     V0_2_0,
+    #[serde(rename = "0.4.0")]
+    V0_4_0,
+}
+
+impl RinstallVersion {
+    // The spec versions supported by this release of rinstall
+    const SUPPORTED: [&'static str; 3] = ["0.1.0", "0.2.0", "0.4.0"];
+    /// The latest spec version, used for the generated spec files
+    pub const LATEST: &'static str = Self::SUPPORTED[Self::SUPPORTED.len() - 1];
+
+    /// Check that the version of the spec file is supported by this release of rinstall
+    /// It must be called before parsing the spec file, because newer versions can contain
+    /// entries that are unknown to this release
+    pub fn check(spec_file: &str) -> Result<()> {
+        let spec: Value = serde_yaml::from_str(spec_file).context("unable to parse spec file")?;
+        let version = spec
+            .get("rinstall")
+            .context("the spec file does not contain the 'rinstall' version")?;
+        let version = version
+            .as_str()
+            .with_context(|| format!("the rinstall version {version:?} must be a string"))?;
+        if Self::SUPPORTED.contains(&version) {
+            return Ok(());
+        }
+
+        let latest = Version::parse(Self::LATEST).unwrap();
+        match Version::parse(version) {
+            Ok(version) if version > latest => {
+                bail!("spec version {version} is newer than the latest supported ({latest})")
+            }
+            _ => bail!(
+                "unsupported rinstall version {version} in spec file, use one of: {}",
+                Self::SUPPORTED.join(", ")
+            ),
+        }
+    }
 }
 
 impl From<&RinstallVersion> for Version {
@@ -23,6 +64,7 @@ impl From<&RinstallVersion> for Version {
         match val {
             RinstallVersion::V0_1_0 => Version::new(0, 1, 0),
             RinstallVersion::V0_2_0 => Version::new(0, 2, 0),
+            RinstallVersion::V0_4_0 => Version::new(0, 4, 0),
         }
     }
 }
@@ -36,29 +78,193 @@ pub struct InstallSpec {
     pub packages: HashMap<String, Package>,
 }
 
+// The [package] keys used as default values for the packages in Cargo.toml
+const CARGO_METADATA_KEYS: [&str; 4] = ["version", "license", "description", "homepage"];
+
+/// Convert [package.metadata.rinstall] of Cargo.toml into a spec file, returns
+/// None if the crate has no rinstall metadata
+/// The values inherited from the workspace are read from its [workspace.package]
+fn cargo_spec_file(
+    cargo_toml: &str,
+    workspace_package: Option<&toml::Value>,
+) -> Result<Option<String>> {
+    let cargo_toml: toml::Table = toml::from_str(cargo_toml)?;
+    let Some(package) = cargo_toml.get("package") else {
+        return Ok(None);
+    };
+    let Some(metadata) = package
+        .get("metadata")
+        .and_then(|metadata| metadata.get("rinstall"))
+    else {
+        return Ok(None);
+    };
+    let Value::Mapping(mut spec) = serde_yaml::to_value(metadata)? else {
+        bail!("[package.metadata.rinstall] must be a table");
+    };
+
+    // Default to the latest spec version
+    let version = spec
+        .remove("rinstall")
+        .unwrap_or_else(|| RinstallVersion::LATEST.into());
+    // A single package named after the crate can be written without the pkgs table
+    let mut pkgs = if let Some(pkgs) = spec.remove("pkgs") {
+        let Value::Mapping(pkgs) = pkgs else {
+            bail!("[package.metadata.rinstall.pkgs] must be a table");
+        };
+        pkgs
+    } else {
+        let name = package
+            .get("name")
+            .and_then(|name| name.as_str())
+            .context("the crate does not have a name")?;
+        Mapping::from_iter([(name.into(), Value::Mapping(spec))])
+    };
+
+    // The metadata keys are only available in newer spec versions
+    let has_metadata = version
+        .as_str()
+        .and_then(|version| Version::parse(version).ok())
+        .is_some_and(|version| version >= Version::new(0, 4, 0));
+    for pkg in pkgs.values_mut() {
+        let Value::Mapping(pkg) = pkg else {
+            continue;
+        };
+        // The binaries are in the cargo target directory
+        if !pkg.contains_key("type") {
+            pkg.insert("type".into(), "rust".into());
+        }
+        if has_metadata {
+            for key in CARGO_METADATA_KEYS {
+                // Resolve the values inherited from the workspace, i.e. version.workspace = true
+                let value = package.get(key).and_then(|value| {
+                    if value.get("workspace").and_then(toml::Value::as_bool) == Some(true) {
+                        workspace_package?.get(key)?.as_str()
+                    } else {
+                        value.as_str()
+                    }
+                });
+                if let Some(value) = value {
+                    if !pkg.contains_key(key) {
+                        pkg.insert(key.into(), value.into());
+                    }
+                }
+            }
+        }
+    }
+
+    let spec = Mapping::from_iter([
+        ("rinstall".into(), version),
+        ("pkgs".into(), Value::Mapping(pkgs)),
+    ]);
+    Ok(Some(serde_yaml::to_string(&spec)?))
+}
+
 impl InstallSpec {
-    pub fn new_from_path(package_dir: &Utf8Path) -> Result<Self> {
+    /// Read the spec file of a project, along with the path it has been read from
+    /// The spec file is generated from Cargo.toml when there is no install.yml
+    pub fn read_spec_file(package_dir: &Utf8Path) -> Result<(Utf8PathBuf, String)> {
         // Try root/install.yml and root/.package/install.yml files
-        let install_spec = {
-            let install_spec = package_dir.join("install.yml");
+        for install_spec in [
+            package_dir.join("install.yml"),
+            package_dir.join(".package").join("install.yml"),
+        ] {
             if install_spec.exists() {
-                install_spec
+                let spec_file = fs::read_to_string(&install_spec)
+                    .with_context(|| format!("unable to read file {:?}", install_spec))?;
+                return Ok((install_spec, spec_file));
+            }
+        }
+
+        let cargo_toml = package_dir.join("Cargo.toml");
+        if cargo_toml.exists() {
+            // The crate can be a member of a workspace, defined in a parent directory
+            let workspace_toml = workspace_root(package_dir).join("Cargo.toml");
+            let workspace = if workspace_toml.exists() {
+                fs::read_to_string(&workspace_toml)
+                    .with_context(|| format!("unable to read file {:?}", workspace_toml))?
+                    .parse::<toml::Table>()
+                    .with_context(|| format!("unable to parse {workspace_toml}"))?
             } else {
-                let install_spec = package_dir.join(".package").join("install.yml");
-                if install_spec.exists() {
-                    install_spec
-                } else {
-                    bail!("unable to find 'install.yml' file");
-                }
+                toml::Table::new()
+            };
+            let spec_file = cargo_spec_file(
+                &fs::read_to_string(&cargo_toml)
+                    .with_context(|| format!("unable to read file {:?}", cargo_toml))?,
+                workspace
+                    .get("workspace")
+                    .and_then(|workspace| workspace.get("package")),
+            )
+            .with_context(|| format!("unable to parse {cargo_toml}"))?;
+            if let Some(spec_file) = spec_file {
+                return Ok((cargo_toml, spec_file));
             }
+        }
+
+        bail!("unable to find 'install.yml' file or [package.metadata.rinstall] in Cargo.toml");
+    }
+
+    /// Replace the entries set to `auto` in the selected rust packages with the
+    /// files discovered in the cargo project, so that the spec file can be used
+    /// without the project, i.e. from a tarball
+    pub fn expand_auto_entries(
+        spec_file: &str,
+        selected: &[String],
+        rust_directories: &RustDirectories,
+    ) -> Result<String> {
+        let mut spec: Value =
+            serde_yaml::from_str(spec_file).context("unable to parse spec file")?;
+        let Some(Value::Mapping(pkgs)) = spec.get_mut("pkgs") else {
+            return Ok(spec_file.to_string());
         };
-        Ok(serde_yaml::from_str(
-            &fs::read_to_string(&install_spec)
-                .with_context(|| format!("unable to read file {:?}", install_spec))?,
-        )?)
+        let mut expanded = false;
+        for (name, pkg) in pkgs.iter_mut() {
+            let (Some(name), Value::Mapping(pkg)) = (name.as_str(), pkg) else {
+                continue;
+            };
+            if !selected.is_empty() && !selected.iter().any(|pkg| pkg == name)
+                || pkg.get("type").and_then(Value::as_str) != Some("rust")
+            {
+                continue;
+            }
+            for key in AUTO_ENTRIES {
+                if pkg.get(key).and_then(Value::as_str) != Some("auto") {
+                    continue;
+                }
+                let sources = |key: &str| -> Result<Value> {
+                    Ok(discover_sources(rust_directories, name, key)?
+                        .into_iter()
+                        .map(|source| Value::from(source.as_str()))
+                        .collect())
+                };
+                let value = if key == "completions" {
+                    Value::Mapping(
+                        ["bash", "elvish", "fish", "zsh"]
+                            .into_iter()
+                            .map(|shell| Ok((shell.into(), sources(&format!("{key}:{shell}"))?)))
+                            .collect::<Result<_>>()?,
+                    )
+                } else {
+                    sources(key)?
+                };
+                pkg.insert(key.into(), value);
+                expanded = true;
+            }
+        }
+
+        if !expanded {
+            return Ok(spec_file.to_string());
+        }
+        Ok(serde_yaml::to_string(&spec)?)
+    }
+
+    pub fn new_from_path(package_dir: &Utf8Path) -> Result<Self> {
+        let (install_spec, spec_file) = Self::read_spec_file(package_dir)?;
+        Self::new_from_string(spec_file)
+            .with_context(|| format!("unable to parse spec file {install_spec}"))
     }
 
     pub fn new_from_string(spec_file: String) -> Result<Self> {
+        RinstallVersion::check(&spec_file)?;
         Ok(serde_yaml::from_str(&spec_file)?)
     }
 
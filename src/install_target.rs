@@ -1,4 +1,9 @@
 use camino::{Utf8Path, Utf8PathBuf};
+use color_eyre::{eyre::bail, Result};
+use colored::Colorize;
+use log::warn;
+
+use crate::{package::Type, project::build_directories};
 
 // SpecFile entries
 pub struct InstallEntry {
@@ -9,9 +14,30 @@ pub struct InstallEntry {
     pub destination: Utf8PathBuf,
     // Do we apply templating here?
     pub templating: bool,
+    // Remove the symbols and debug information of ELF files
+    pub strip: bool,
+    // The interpreter replacing the one in the first line of the scripts
+    pub shebang: Option<String>,
+    // The command printing the contents of the file, it replaces the source
+    pub generate: Option<String>,
     // Do this file replace the contents by default?
     // i.e. in config it's not replaceable
     pub replace: bool,
+    pub kind: EntryKind,
+    // The type of the package, used to look for the source in the build directory
+    pub pkg_type: Type,
+}
+
+// The kind of file installed, used when generating the manifests for packagers
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    Default,
+    Executable,
+    Library,
+    PamModule,
+    Man,
+    Docs,
+    Licenses,
 }
 
 #[derive(Clone, Copy)]
@@ -21,6 +47,42 @@ pub enum FilesPolicy {
 }
 
 impl InstallEntry {
+    pub fn with_kind(
+        self,
+        kind: EntryKind,
+    ) -> Self {
+        Self { kind, ..self }
+    }
+
+    pub fn ensure_source_exists(&self) -> Result<()> {
+        if self.full_source.exists() {
+            // The built files missing from the build directory and found in the project
+            // directory instead can be left over by an older in-tree build
+            if let Some(builddir) =
+                build_directories(self.pkg_type).and_then(|dirs| dirs.builddir.as_deref())
+            {
+                if matches!(
+                    self.kind,
+                    EntryKind::Executable | EntryKind::Library | EntryKind::PamModule
+                ) && !self.full_source.starts_with(builddir)
+                {
+                    warn!(
+                        "{} does not exist in {builddir}, using the one in the project directory",
+                        self.source.as_str().yellow().bold()
+                    );
+                }
+            }
+            return Ok(());
+        }
+        match self.pkg_type.builddir() {
+            Some(builddir) => bail!(
+                "File {:?} does not exist in {builddir} nor in the project directory, build the project or use --build",
+                self.source
+            ),
+            None => bail!("File {:?} does not exist", self.source),
+        }
+    }
+
     pub fn destination_for_file(&self) -> Utf8PathBuf {
         if self.destination.as_str().ends_with('/') {
             self.destination.join(self.source.file_name().unwrap())
@@ -29,6 +91,21 @@ impl InstallEntry {
         }
     }
 
+    /// Returns the destination of a file contained in a tarball, or None if the
+    /// file is not part of this entry
+    pub fn destination_for_tarball_path(
+        &self,
+        path: &Utf8Path,
+    ) -> Option<Utf8PathBuf> {
+        if self.source == path {
+            Some(self.destination_for_file())
+        } else {
+            path.strip_prefix(&self.source)
+                .ok()
+                .map(|relative_path| self.destination.join(relative_path))
+        }
+    }
+
     pub fn destination_for_file_in_directory(
         &self,
         full_path: &Utf8Path,
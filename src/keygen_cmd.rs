@@ -0,0 +1,10 @@
+#[derive(Args, Clone)]
+pub struct KeygenCmd {
+    #[clap(
+        help = concat!("Path to the secret key to generate; the public key will be",
+                       " written next to it with the suffix .pub")
+    )]
+    pub secret_key: std::path::PathBuf,
+    #[clap(short = 'f', long = "force", help = "Overwrite the keys if they already exist")]
+    pub force: bool,
+}
@@ -0,0 +1,40 @@
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::Args;
+use color_eyre::{
+    eyre::{ensure, ContextCompat},
+    Result,
+};
+use colored::Colorize;
+use log::info;
+
+use crate::signature::SecretKey;
+
+include!("keygen_cmd.rs");
+
+impl KeygenCmd {
+    pub fn run(self) -> Result<()> {
+        let secret_key_path = Utf8Path::from_path(&self.secret_key)
+            .with_context(|| format!("{:?} contains invalid UTF-8 characters", self.secret_key))?;
+        let public_key_path = Utf8PathBuf::from(format!("{secret_key_path}.pub"));
+        for path in [secret_key_path, &public_key_path] {
+            ensure!(
+                self.force || !path.exists(),
+                "{path} already exists, add --force to overwrite it"
+            );
+        }
+
+        let secret_key = SecretKey::generate()?;
+        info!(
+            "Writing secret key {}",
+            secret_key_path.as_str().cyan().bold()
+        );
+        secret_key.save(secret_key_path)?;
+        info!(
+            "Writing public key {}",
+            public_key_path.as_str().cyan().bold()
+        );
+        secret_key.public_key().save(&public_key_path)?;
+
+        Ok(())
+    }
+}
@@ -1,15 +1,31 @@
+mod deb_cmd_impl;
 mod dirs;
 mod dirs_config_impl;
+mod elf_check;
+mod env_cmd_impl;
+mod export_cmd_impl;
+mod export_script_cmd_impl;
 mod icon;
+mod inspect_cmd_impl;
 mod install_cmd_impl;
 mod install_spec;
 mod install_target;
+mod keygen_cmd_impl;
+mod man_page;
+mod manifest;
 mod opts_impl;
 mod package;
 mod package_info;
+mod pkg_arch_cmd_impl;
 mod project;
+mod sbom;
+mod sbom_cmd_impl;
+mod signature;
 mod simple_logger;
+mod staging;
 mod string_or_struct;
+mod strip;
+mod tarball;
 mod tarball_cmd_impl;
 mod templating;
 mod uninstall_impl;
@@ -22,11 +38,19 @@ use clap::Parser;
 use color_eyre::Result;
 use log::LevelFilter;
 
+pub use deb_cmd_impl::DebCmd;
 use dirs::Dirs;
 pub use dirs_config_impl::DirsConfig;
+pub use env_cmd_impl::EnvCmd;
+pub use export_cmd_impl::ExportCmd;
+pub use export_script_cmd_impl::ExportScriptCmd;
+pub use inspect_cmd_impl::InspectCmd;
 pub use install_cmd_impl::InstallCmd;
+pub use keygen_cmd_impl::KeygenCmd;
 pub use opts_impl::{Opts, SubCommand};
 use package::Package;
+pub use pkg_arch_cmd_impl::PkgArchCmd;
+pub use sbom_cmd_impl::SbomCmd;
 use simple_logger::SimpleLogger;
 pub use tarball_cmd_impl::TarballCmd;
 pub use uninstall_impl::Uninstall;
@@ -43,6 +67,14 @@ fn main() -> Result<()> {
         }
         SubCommand::Install(install) => install.run()?,
         SubCommand::Tarball(tarball) => tarball.run()?,
+        SubCommand::Export(export) => export.run()?,
+        SubCommand::Inspect(inspect) => inspect.run()?,
+        SubCommand::Keygen(keygen) => keygen.run()?,
+        SubCommand::Deb(deb) => deb.run()?,
+        SubCommand::PkgArch(pkg_arch) => pkg_arch.run()?,
+        SubCommand::ExportScript(export_script) => export_script.run()?,
+        SubCommand::Sbom(sbom) => sbom.run()?,
+        SubCommand::Env(env) => env.run()?,
     }
 
     Ok(())
@@ -0,0 +1,450 @@
+use camino::Utf8Path;
+use clap::ValueEnum;
+use color_eyre::{
+    eyre::{bail, ensure, ContextCompat},
+    Result,
+};
+use pulldown_cmark::{Event, HeadingLevel, LinkType, Parser, Tag};
+
+use crate::{dirs_config_impl::ManCompression, utils::timestamp};
+
+/// The formats of the man pages that are rendered to roff when installing them
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ManFormat {
+    Scdoc,
+    Markdown,
+}
+
+impl ManFormat {
+    /// The format of a man page by its extension, None when it is already roff
+    pub fn from_path(path: &Utf8Path) -> Option<Self> {
+        match path.extension() {
+            Some("scd") => Some(ManFormat::Scdoc),
+            Some("md") => Some(ManFormat::Markdown),
+            _ => None,
+        }
+    }
+
+    /// Render a man page to roff, `page` is its installed name, i.e. "foo.1"
+    pub fn render(
+        self,
+        source: &str,
+        page: &str,
+    ) -> Result<String> {
+        // The page has already been rendered, i.e. when installing from a tarball
+        if source.starts_with(".TH ") {
+            return Ok(source.to_owned());
+        }
+        match self {
+            ManFormat::Scdoc => render_scdoc(source),
+            ManFormat::Markdown => {
+                let (name, section) = page
+                    .rsplit_once('.')
+                    .with_context(|| format!("unable to get the section of man page {page}"))?;
+                Ok(render_markdown(source, name, section))
+            }
+        }
+    }
+}
+
+/// The name of an installed man page without the extension added by its
+/// compression, i.e. "foo.1" for "foo.1.gz"
+pub fn page_name(file_name: &str) -> &str {
+    page_compression(file_name).map_or(file_name, |(page, _)| page)
+}
+
+/// The compression of an installed man page, guessed from its extension, and
+/// the name of the page without it
+pub fn page_compression(file_name: &str) -> Option<(&str, ManCompression)> {
+    ManCompression::value_variants()
+        .iter()
+        .find_map(|compression| {
+            let page = file_name.strip_suffix(compression.extension()?)?;
+            Some((page.strip_suffix('.')?, *compression))
+        })
+}
+
+// Write the requests on their own line and escape the text between them
+struct Roff {
+    out: String,
+    // The fonts currently used, the last one is the active one
+    fonts: Vec<char>,
+}
+
+impl Roff {
+    fn new(
+        name: &str,
+        section: &str,
+        extra: &[&str],
+    ) -> Self {
+        let mut roff = Self {
+            out: String::new(),
+            fonts: vec!['R'],
+        };
+        let mut title = format!(".TH \"{name}\" \"{section}\" \"{}\"", &timestamp()[..10]);
+        for extra in extra {
+            title.push_str(&format!(" \"{extra}\""));
+        }
+        roff.request(&title);
+
+        roff
+    }
+
+    fn newline(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+    }
+
+    fn request(
+        &mut self,
+        request: &str,
+    ) {
+        self.newline();
+        self.out.push_str(request);
+        self.out.push('\n');
+    }
+
+    // A request followed by its arguments, i.e. the title of a section
+    fn start_request(
+        &mut self,
+        request: &str,
+    ) {
+        self.newline();
+        self.out.push_str(request);
+        self.out.push(' ');
+    }
+
+    fn text(
+        &mut self,
+        text: &str,
+    ) {
+        for c in text.chars() {
+            // A line starting with these is a request
+            if (self.out.is_empty() || self.out.ends_with('\n')) && (c == '.' || c == '\'') {
+                self.out.push_str("\\&");
+            }
+            match c {
+                '\\' => self.out.push_str("\\e"),
+                '-' => self.out.push_str("\\-"),
+                _ => self.out.push(c),
+            }
+        }
+    }
+
+    fn push_font(
+        &mut self,
+        font: char,
+    ) {
+        self.fonts.push(font);
+        self.out.push_str(&format!("\\f{font}"));
+    }
+
+    fn pop_font(&mut self) {
+        if self.fonts.len() > 1 {
+            self.fonts.pop();
+        }
+        self.out
+            .push_str(&format!("\\f{}", self.fonts.last().unwrap()));
+    }
+}
+
+// The name and the section are taken from the file name, Markdown has no preamble
+fn render_markdown(
+    source: &str,
+    name: &str,
+    section: &str,
+) -> String {
+    let mut roff = Roff::new(&name.to_uppercase(), section, &[]);
+    // The lists being rendered, along with the number of their next item
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    // The first paragraph of an item is on the same line as its tag
+    let mut item_start = false;
+    let mut links = Vec::new();
+    for event in Parser::new(source) {
+        match event {
+            Event::Start(Tag::Heading(level, ..)) => {
+                roff.start_request(if level == HeadingLevel::H1 {
+                    ".SH"
+                } else {
+                    ".SS"
+                });
+            }
+            Event::Start(Tag::Paragraph) => {
+                let after_tag = std::mem::take(&mut item_start);
+                if !after_tag {
+                    roff.request(if lists.is_empty() { ".PP" } else { ".sp" });
+                }
+            }
+            Event::End(Tag::Heading(..) | Tag::Paragraph | Tag::Item) => {
+                item_start = false;
+                roff.newline();
+            }
+            Event::Start(Tag::List(start)) => {
+                if !lists.is_empty() {
+                    roff.request(".RS 4");
+                }
+                lists.push(start);
+            }
+            Event::End(Tag::List(_)) => {
+                lists.pop();
+                if !lists.is_empty() {
+                    roff.request(".RE");
+                }
+            }
+            Event::Start(Tag::Item) => {
+                let tag = match lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}.", *number - 1)
+                    }
+                    _ => "\\(bu".to_string(),
+                };
+                roff.request(&format!(".IP {tag} 4"));
+                item_start = true;
+            }
+            Event::Start(Tag::CodeBlock(_)) => {
+                item_start = false;
+                roff.request(".sp");
+                roff.request(".RS 4");
+                roff.request(".nf");
+            }
+            Event::End(Tag::CodeBlock(_)) => {
+                roff.request(".fi");
+                roff.request(".RE");
+            }
+            Event::Start(Tag::BlockQuote) => roff.request(".RS 4"),
+            Event::End(Tag::BlockQuote) => roff.request(".RE"),
+            Event::Start(Tag::Emphasis) => roff.push_font('I'),
+            Event::Start(Tag::Strong) => roff.push_font('B'),
+            Event::End(Tag::Emphasis | Tag::Strong) => roff.pop_font(),
+            Event::Start(Tag::Link(link_type, url, _)) => links.push((link_type, url)),
+            Event::End(Tag::Link(..)) => {
+                // The text of the autolinks is the url itself
+                if let Some((link_type, url)) = links.pop() {
+                    if !matches!(link_type, LinkType::Autolink | LinkType::Email)
+                        && !url.starts_with('#')
+                    {
+                        roff.text(&format!(" <{url}>"));
+                    }
+                }
+            }
+            Event::Text(text) => roff.text(&text),
+            Event::Code(code) => {
+                roff.push_font('B');
+                roff.text(&code);
+                roff.pop_font();
+            }
+            Event::SoftBreak => roff.newline(),
+            Event::HardBreak => roff.request(".br"),
+            Event::Rule => roff.request(".sp"),
+            _ => {}
+        }
+    }
+
+    roff.out
+}
+
+// Render the scdoc(5) syntax, except for the tables
+fn render_scdoc(source: &str) -> Result<String> {
+    let mut lines = source
+        .lines()
+        .enumerate()
+        .map(|(number, line)| (number + 1, line))
+        .filter(|(_, line)| !line.starts_with(';'));
+    let (_, preamble) = lines.next().context("the scdoc man page is empty")?;
+    // i.e. foo(1) "footer" "header"
+    let (name, rest) = preamble
+        .split_once('(')
+        .with_context(|| format!("invalid preamble {preamble:?}, expected name(section)"))?;
+    let (section, rest) = rest
+        .split_once(')')
+        .with_context(|| format!("invalid preamble {preamble:?}, expected name(section)"))?;
+    let extra = rest.split('"').skip(1).step_by(2).collect::<Vec<_>>();
+    let mut roff = Roff::new(name, section, &extra);
+
+    let mut paragraph = false;
+    let mut indent = 0;
+    let mut list_number = 0;
+    // The indentation of the literal block being rendered
+    let mut literal = None;
+    let mut last_line = 0;
+    for (line_number, line) in lines {
+        last_line = line_number;
+        if let Some(literal_indent) = literal {
+            let content = strip_tabs(line, literal_indent);
+            if content.trim_start() == "```" {
+                roff.request(".fi");
+                roff.request(".RE");
+                literal = None;
+            } else {
+                roff.text(content);
+                roff.out.push('\n');
+            }
+            continue;
+        }
+
+        if line.trim().is_empty() || line.starts_with('#') {
+            ensure!(
+                roff.fonts.len() == 1,
+                "line {line_number}: unterminated formatting"
+            );
+            paragraph = false;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some((request, heading)) = line
+            .strip_prefix("## ")
+            .map(|heading| (".SS", heading))
+            .or_else(|| line.strip_prefix("# ").map(|heading| (".SH", heading)))
+        {
+            for _ in 0..std::mem::take(&mut indent) {
+                roff.request(".RE");
+            }
+            roff.start_request(request);
+            roff.text(heading.trim());
+            roff.newline();
+            continue;
+        }
+
+        let tabs = line.len() - line.trim_start_matches('\t').len();
+        let content = &line[tabs..];
+        while indent < tabs {
+            roff.request(".RS 4");
+            indent += 1;
+        }
+        while indent > tabs {
+            roff.request(".RE");
+            indent -= 1;
+        }
+
+        if content.trim_end() == "```" {
+            roff.request(".sp");
+            roff.request(".RS 4");
+            roff.request(".nf");
+            literal = Some(tabs);
+            paragraph = false;
+            continue;
+        }
+        if content.starts_with(['[', '|', ']']) {
+            bail!("line {line_number}: the scdoc tables are not supported");
+        }
+
+        let content = if let Some(item) = content.strip_prefix("- ") {
+            roff.request(".IP \\(bu 4");
+            item
+        } else if let Some(item) = content.strip_prefix(". ") {
+            list_number += 1;
+            roff.request(&format!(".IP {list_number}. 4"));
+            item
+        } else {
+            if !paragraph {
+                roff.request(".PP");
+                list_number = 0;
+            }
+            content
+        };
+        paragraph = true;
+        scdoc_inline(&mut roff, content.trim(), line_number)?;
+    }
+
+    ensure!(
+        literal.is_none(),
+        "line {last_line}: unterminated literal block"
+    );
+    ensure!(
+        roff.fonts.len() == 1,
+        "line {last_line}: unterminated formatting"
+    );
+    for _ in 0..indent {
+        roff.request(".RE");
+    }
+
+    Ok(roff.out)
+}
+
+fn strip_tabs(
+    line: &str,
+    tabs: usize,
+) -> &str {
+    let mut line = line;
+    for _ in 0..tabs {
+        line = line.strip_prefix('\t').unwrap_or(line);
+    }
+    line
+}
+
+// Render *bold* and _underline_ text, a line ending with ++ is followed by a line break
+fn scdoc_inline(
+    roff: &mut Roff,
+    text: &str,
+    line_number: usize,
+) -> Result<()> {
+    let (text, line_break) = match text.strip_suffix("++") {
+        Some(text) => (text.trim_end(), true),
+        None => (text, false),
+    };
+    let mut chars = text.chars();
+    let mut previous: Option<char> = None;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let escaped = chars
+                    .next()
+                    .with_context(|| format!("line {line_number}: nothing to escape"))?;
+                roff.text(&escaped.to_string());
+            }
+            '*' if roff.fonts.last() == Some(&'B') => roff.pop_font(),
+            '*' => roff.push_font('B'),
+            // The underscores inside of the words are kept, i.e. snake_case
+            '_' if roff.fonts.last() == Some(&'I')
+                && !chars.clone().next().is_some_and(char::is_alphanumeric) =>
+            {
+                roff.pop_font()
+            }
+            '_' if roff.fonts.last() != Some(&'I')
+                && !previous.is_some_and(char::is_alphanumeric) =>
+            {
+                roff.push_font('I')
+            }
+            _ => roff.text(&c.to_string()),
+        }
+        previous = Some(c);
+    }
+    if line_break {
+        roff.request(".br");
+    } else {
+        roff.newline();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scdoc_line(text: &str) -> String {
+        let mut roff = Roff {
+            out: String::new(),
+            fonts: vec!['R'],
+        };
+        scdoc_inline(&mut roff, text, 1).unwrap();
+        roff.out
+    }
+
+    #[test]
+    fn render_roff() {
+        let page = ".TH \"foo\" \"1\" \"2024-01-01\"\nfoo\n";
+        assert_eq!(ManFormat::Scdoc.render(page, "foo.1").unwrap(), page);
+        assert_eq!(ManFormat::Markdown.render(page, "foo.1").unwrap(), page);
+    }
+
+    #[test]
+    fn scdoc_underline() {
+        assert_eq!(scdoc_line("_foo_ bar"), "\\fIfoo\\fR bar\n");
+        assert_eq!(scdoc_line("_snake_case_"), "\\fIsnake_case\\fR\n");
+        assert_eq!(scdoc_line("foo_bar"), "foo_bar\n");
+    }
+}
@@ -0,0 +1,260 @@
+use std::{collections::BTreeSet, fmt::Write, fs};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use color_eyre::{
+    eyre::{bail, Context, ContextCompat},
+    Result,
+};
+
+use crate::{
+    install_target::{EntryKind, InstallEntry},
+    sbom::{self, SbomFile, SbomPackage},
+    utils::append_destdir,
+};
+
+#[derive(Clone, Copy)]
+pub enum ManifestFormat {
+    // The %files section of a RPM spec file
+    Rpm,
+    // The files listed in DEBIAN/conffiles
+    DebConffiles,
+    // debian/<pkg>.install, used by dh_install
+    DebInstall,
+    // debian/<pkg>.dirs, used by dh_installdirs
+    DebDirs,
+    // The backup array of a PKGBUILD
+    ArchBackup,
+    // A SPDX JSON document listing the files and their checksums
+    Spdx,
+    // A CycloneDX JSON document listing the files and their checksums
+    CycloneDx,
+}
+
+/// A manifest requested with --files-manifest <format>=<path>
+pub struct FilesManifest {
+    pub format: ManifestFormat,
+    pub path: Utf8PathBuf,
+}
+
+impl FilesManifest {
+    pub fn parse(s: &str) -> Result<Self> {
+        let (format, path) = s
+            .split_once('=')
+            .with_context(|| format!("{s} is not in the form <format>=<path>"))?;
+        if path.is_empty() {
+            bail!("the path of the {format} manifest is empty");
+        }
+        let format = match format {
+            "rpm" => ManifestFormat::Rpm,
+            "deb-conffiles" => ManifestFormat::DebConffiles,
+            "deb-install" => ManifestFormat::DebInstall,
+            "deb-dirs" => ManifestFormat::DebDirs,
+            "arch-backup" => ManifestFormat::ArchBackup,
+            "spdx" => ManifestFormat::Spdx,
+            "cyclonedx" => ManifestFormat::CycloneDx,
+            _ => bail!(
+                "{format} is not a valid manifest format, possible values are: {}",
+                "rpm, deb-conffiles, deb-install, deb-dirs, arch-backup, spdx, cyclonedx"
+            ),
+        };
+
+        Ok(Self {
+            format,
+            path: Utf8PathBuf::from(path),
+        })
+    }
+
+    /// Write the manifest, the installed files are read from the destdir
+    pub fn write(
+        &self,
+        manifests: &[Manifest],
+        destdir: Option<&str>,
+    ) -> Result<()> {
+        let contents = match self.format {
+            ManifestFormat::Rpm => rpm_files(manifests),
+            ManifestFormat::DebConffiles => deb_conffiles(manifests),
+            ManifestFormat::DebInstall => deb_install(manifests),
+            ManifestFormat::DebDirs => deb_dirs(manifests),
+            ManifestFormat::ArchBackup => arch_backup(manifests),
+            ManifestFormat::Spdx => sbom::spdx(&sbom_packages(manifests, destdir)?),
+            ManifestFormat::CycloneDx => sbom::cyclonedx(&sbom_packages(manifests, destdir)?),
+        };
+        fs::write(&self.path, contents)
+            .with_context(|| format!("unable to write manifest to {:?}", self.path))
+    }
+}
+
+pub struct ManifestFile {
+    // The destination of the file, without the destdir
+    pub path: Utf8PathBuf,
+    pub kind: EntryKind,
+    pub replace: bool,
+}
+
+/// The files installed by a package
+pub struct Manifest {
+    pub pkg_name: String,
+    pub version: Option<String>,
+    pub homepage: Option<String>,
+    pub license: Option<String>,
+    pub files: Vec<ManifestFile>,
+}
+
+impl Manifest {
+    pub fn new(pkg_name: &str) -> Self {
+        Self {
+            pkg_name: pkg_name.to_string(),
+            version: None,
+            homepage: None,
+            license: None,
+            files: Vec::new(),
+        }
+    }
+
+    pub fn add_file(
+        &mut self,
+        destination: &Utf8Path,
+        entry: &InstallEntry,
+    ) {
+        self.files.push(ManifestFile {
+            path: destination.to_path_buf(),
+            kind: entry.kind,
+            replace: entry.replace,
+        });
+    }
+
+    /// Returns the directories owned by the package, i.e. the ones named after
+    /// the package (like /usr/share/<pkg-name>) and their subdirectories
+    pub fn package_dirs(&self) -> BTreeSet<Utf8PathBuf> {
+        let mut dirs = BTreeSet::new();
+        for file in &self.files {
+            let ancestors = file.path.ancestors().skip(1).collect::<Vec<_>>();
+            // Start from the outermost directory named after the package
+            if let Some(pos) = ancestors
+                .iter()
+                .rposition(|dir| dir.file_name() == Some(&self.pkg_name))
+            {
+                dirs.extend(ancestors[..=pos].iter().map(|dir| dir.to_path_buf()));
+            }
+        }
+
+        dirs
+    }
+
+    /// Returns the configuration files, which must not be overwritten on upgrade
+    pub fn config_files(&self) -> impl Iterator<Item = &Utf8Path> {
+        self.files
+            .iter()
+            .filter(|file| !file.replace)
+            .map(|file| file.path.as_path())
+    }
+}
+
+// Paths relative to the destdir, as used by debhelper and makepkg
+fn relative_path(path: &Utf8Path) -> &str {
+    path.as_str().trim_start_matches('/')
+}
+
+// Quote the path when needed and escape the macros
+fn rpm_path(path: &Utf8Path) -> String {
+    let path = path.as_str().replace('%', "%%");
+    if path.contains(char::is_whitespace) {
+        format!("\"{path}\"")
+    } else {
+        path
+    }
+}
+
+fn rpm_files(manifests: &[Manifest]) -> String {
+    let mut res = String::new();
+    for manifest in manifests {
+        for dir in manifest.package_dirs() {
+            writeln!(res, "%dir {}", rpm_path(&dir)).unwrap();
+        }
+        for file in &manifest.files {
+            let directive = match file.kind {
+                EntryKind::Docs => "%doc ",
+                EntryKind::Licenses => "%license ",
+                _ if !file.replace => "%config(noreplace) ",
+                _ => "",
+            };
+            let path = rpm_path(&file.path);
+            // rpmbuild compresses the man pages after %install
+            let glob = if file.kind == EntryKind::Man { "*" } else { "" };
+            writeln!(res, "{directive}{path}{glob}").unwrap();
+        }
+    }
+
+    res
+}
+
+fn deb_conffiles(manifests: &[Manifest]) -> String {
+    manifests
+        .iter()
+        .flat_map(Manifest::config_files)
+        .map(|path| format!("{path}\n"))
+        .collect()
+}
+
+// Quote the paths containing whitespaces, supported by debhelper compat >= 13
+fn deb_path(path: &Utf8Path) -> String {
+    let path = relative_path(path);
+    if path.contains(char::is_whitespace) {
+        format!("\"{path}\"")
+    } else {
+        path.to_string()
+    }
+}
+
+fn deb_install(manifests: &[Manifest]) -> String {
+    manifests
+        .iter()
+        .flat_map(|manifest| &manifest.files)
+        .map(|file| format!("{}\n", deb_path(&file.path)))
+        .collect()
+}
+
+fn deb_dirs(manifests: &[Manifest]) -> String {
+    manifests
+        .iter()
+        .flat_map(Manifest::package_dirs)
+        .map(|dir| format!("{}\n", deb_path(&dir)))
+        .collect()
+}
+
+fn arch_backup(manifests: &[Manifest]) -> String {
+    let files = manifests
+        .iter()
+        .flat_map(Manifest::config_files)
+        .map(|path| format!("'{}'", relative_path(path).replace('\'', r"'\''")))
+        .collect::<Vec<_>>();
+    format!("backup=({})\n", files.join(" "))
+}
+
+fn sbom_packages(
+    manifests: &[Manifest],
+    destdir: Option<&str>,
+) -> Result<Vec<SbomPackage>> {
+    manifests
+        .iter()
+        .map(|manifest| {
+            Ok(SbomPackage {
+                name: manifest.pkg_name.clone(),
+                version: manifest.version.clone(),
+                homepage: manifest.homepage.clone(),
+                license: manifest.license.clone(),
+                files: manifest
+                    .files
+                    .iter()
+                    .map(|file| {
+                        SbomFile::new(
+                            &file.path,
+                            &append_destdir(&file.path, destdir),
+                            file.kind == EntryKind::Licenses,
+                        )
+                    })
+                    .collect::<Result<_>>()?,
+            })
+        })
+        .collect()
+}
@@ -32,4 +32,20 @@ pub enum SubCommand {
     Uninstall(Uninstall),
     #[clap(about = "Create a tarball of the package")]
     Tarball(Box<TarballCmd>),
+    #[clap(about = "Create a tarball from an installed package")]
+    Export(ExportCmd),
+    #[clap(about = "Show the content of a tarball and optionally extract it")]
+    Inspect(Box<InspectCmd>),
+    #[clap(about = "Generate a key pair used to sign tarballs")]
+    Keygen(KeygenCmd),
+    #[clap(about = "Build a Debian package")]
+    Deb(Box<DebCmd>),
+    #[clap(about = "Build an Arch Linux package")]
+    PkgArch(Box<PkgArchCmd>),
+    #[clap(about = "Generate a shell script or a Makefile installing the packages")]
+    ExportScript(Box<ExportScriptCmd>),
+    #[clap(about = "Generate a software bill of materials of the installed packages")]
+    Sbom(SbomCmd),
+    #[clap(about = "Print the exports adding the user directories to PATH, MANPATH and XDG_DATA_DIRS")]
+    Env(EnvCmd),
 }
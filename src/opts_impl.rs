@@ -1,6 +1,14 @@
 use clap::{Parser, Subcommand};
 
+use crate::DebCmd;
+use crate::EnvCmd;
+use crate::ExportCmd;
+use crate::ExportScriptCmd;
+use crate::InspectCmd;
 use crate::InstallCmd;
+use crate::KeygenCmd;
+use crate::PkgArchCmd;
+use crate::SbomCmd;
 use crate::TarballCmd;
 use crate::Uninstall;
 
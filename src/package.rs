@@ -1,4 +1,4 @@
-use std::str::FromStr;
+use std::{env, ptr::addr_of, str::FromStr};
 
 use camino::{Utf8Path, Utf8PathBuf};
 use color_eyre::{
@@ -8,14 +8,18 @@ use color_eyre::{
 use colored::Colorize;
 use log::warn;
 use semver::{Version, VersionReq};
-use serde::Deserialize;
+use serde::{de, Deserialize, Deserializer};
 use void::Void;
 
 use crate::{
     icon::Icon,
     install_spec::RinstallVersion,
-    install_target::FilesPolicy,
-    project::{DefaultProjectDirectories, ProjectDirectories, RUST_DIRECTORIES},
+    install_target::{EntryKind, FilesPolicy},
+    man_page::{page_name, ManFormat},
+    project::{
+        build_directories, ProjectDirectories, RustDirectories, DEFAULT_DIRECTORIES,
+        RUST_DIRECTORIES,
+    },
     string_or_struct::string_or_struct,
 };
 use crate::{install_target::InstallEntry, Dirs};
@@ -81,6 +85,9 @@ pub struct InnerEntry {
     pub destination: Option<Utf8PathBuf>,
     #[serde(default, rename(deserialize = "tmpl"))]
     pub templating: bool,
+    #[serde(default)]
+    pub strip: bool,
+    pub shebang: Option<String>,
 }
 
 impl InnerEntry {
@@ -89,6 +96,8 @@ impl InnerEntry {
             source,
             destination: None,
             templating: false,
+            strip: false,
+            shebang: None,
         }
     }
 
@@ -105,8 +114,9 @@ impl InnerEntry {
             self.source
         );
 
+        let full_source = pkg_type.sourcepath(&self.source);
         let destination =
-            if self.source.is_file() || self.destination.is_some() {
+            if full_source.is_file() || self.destination.is_some() {
                 install_dir.join(if let Some(destination) = self.destination {
                     ensure!(
                         destination.is_relative(),
@@ -119,16 +129,22 @@ impl InnerEntry {
                     })?)
                 })
             } else {
-                install_dir.to_path_buf()
+                // Add a trailing '/' to mark it as a directory, the files
+                // contained in a tarball are then installed inside of it
+                install_dir.join("")
             };
 
-        let full_source = pkg_type.sourcepath(&self.source);
         Ok(InstallEntry {
             source: self.source,
             full_source,
             destination,
             templating: self.templating,
+            strip: self.strip,
+            shebang: self.shebang,
+            generate: None,
             replace,
+            kind: EntryKind::Default,
+            pkg_type: *pkg_type,
         })
     }
 }
@@ -143,20 +159,21 @@ impl FromStr for InnerEntry {
     }
 }
 
-#[derive(Deserialize, Clone, PartialEq, Debug, Copy)]
+#[derive(Deserialize, Clone, PartialEq, Debug, Copy, Default)]
 pub enum Type {
     #[serde(rename(deserialize = "default"))]
+    #[default]
     Default,
     #[serde(rename(deserialize = "rust"))]
     Rust,
     #[serde(rename(deserialize = "custom"))]
     Custom,
-}
-
-impl Default for Type {
-    fn default() -> Self {
-        Self::Default
-    }
+    #[serde(rename(deserialize = "meson"))]
+    Meson,
+    #[serde(rename(deserialize = "cmake"))]
+    CMake,
+    #[serde(rename(deserialize = "go"))]
+    Go,
 }
 
 impl Type {
@@ -165,8 +182,33 @@ impl Type {
         source: &Utf8Path,
     ) -> Utf8PathBuf {
         match self {
-            Type::Default | Type::Custom => DefaultProjectDirectories.sourcepath(source),
-            Type::Rust => unsafe { RUST_DIRECTORIES.as_ref().unwrap() }.sourcepath(source),
+            Type::Default | Type::Custom => {
+                match unsafe { (*addr_of!(DEFAULT_DIRECTORIES)).as_ref() } {
+                    Some(default_directories) => default_directories.sourcepath(source),
+                    None => source.to_path_buf(),
+                }
+            }
+            Type::Rust => {
+                unsafe { (*addr_of!(RUST_DIRECTORIES)).as_ref().unwrap() }.sourcepath(source)
+            }
+            // The directories are not set when installing from a tarball
+            Type::Meson | Type::CMake | Type::Go => match build_directories(self) {
+                Some(build_directories) => build_directories.sourcepath(source),
+                None => source.to_path_buf(),
+            },
+        }
+    }
+
+    /// The directory containing the build output of the project, if any
+    pub fn builddir(self) -> Option<&'static Utf8Path> {
+        unsafe {
+            match self {
+                Type::Default | Type::Custom => None,
+                Type::Rust => (*addr_of!(RUST_DIRECTORIES)).as_ref()?.outputdir.as_deref(),
+                Type::Meson | Type::CMake | Type::Go => {
+                    build_directories(self)?.builddir.as_deref()
+                }
+            }
         }
     }
 }
@@ -178,6 +220,151 @@ struct Entry {
     pub entry: InnerEntry,
 }
 
+impl Entry {
+    fn new_with_source(source: Utf8PathBuf) -> Self {
+        Self {
+            entry: InnerEntry::new_with_source(source),
+        }
+    }
+}
+
+// The entries of a kind, or `auto` to discover them from the cargo project
+enum MaybeAuto<T> {
+    Auto,
+    Entries(T),
+}
+
+impl<T: Default> Default for MaybeAuto<T> {
+    fn default() -> Self {
+        Self::Entries(T::default())
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for MaybeAuto<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = serde_yaml::Value::deserialize(deserializer)?;
+        if value.as_str() == Some("auto") {
+            Ok(Self::Auto)
+        } else {
+            T::deserialize(value)
+                .map(Self::Entries)
+                .map_err(de::Error::custom)
+        }
+    }
+}
+
+impl MaybeAuto<Vec<Entry>> {
+    fn is_empty(&self) -> bool {
+        matches!(self, Self::Entries(entries) if entries.is_empty())
+    }
+
+    fn resolve(
+        self,
+        pkg_name: &str,
+        key: &str,
+    ) -> Result<Vec<Entry>> {
+        match self {
+            Self::Auto => Ok(discover_sources(rust_directories()?, pkg_name, key)?
+                .into_iter()
+                .map(Entry::new_with_source)
+                .collect()),
+            Self::Entries(entries) => Ok(entries),
+        }
+    }
+}
+
+impl MaybeAuto<Completions> {
+    fn resolve(
+        self,
+        pkg_name: &str,
+    ) -> Result<Completions> {
+        match self {
+            Self::Auto => {
+                let rust_directories = rust_directories()?;
+                let discover = |shell: &str| -> Result<Vec<Entry>> {
+                    Ok(discover_sources(
+                        rust_directories,
+                        pkg_name,
+                        &format!("completions:{shell}"),
+                    )?
+                    .into_iter()
+                    .map(Entry::new_with_source)
+                    .collect())
+                };
+                Ok(Completions {
+                    bash: discover("bash")?,
+                    elvish: discover("elvish")?,
+                    fish: discover("fish")?,
+                    zsh: discover("zsh")?,
+                    generate: None,
+                })
+            }
+            Self::Entries(completions) => Ok(completions),
+        }
+    }
+}
+
+fn rust_directories() -> Result<&'static RustDirectories> {
+    unsafe { (*addr_of!(RUST_DIRECTORIES)).as_ref() }
+        .context("auto entries can only be used in rust packages")
+}
+
+/// The entries that can be set to `auto` in rust packages
+pub const AUTO_ENTRIES: [&str; 4] = ["exe", "libs", "man", "completions"];
+
+/// Discover the sources of an entry set to `auto` from the cargo project,
+/// the key is the name of the entry, i.e. exe or completions:bash
+pub fn discover_sources(
+    rust_directories: &RustDirectories,
+    pkg_name: &str,
+    key: &str,
+) -> Result<Vec<Utf8PathBuf>> {
+    // The completions generated by clap_complete, i.e. in rinstall build.rs
+    let completions = |matches: fn(&Utf8Path) -> bool| -> Result<Vec<Utf8PathBuf>> {
+        Ok(rust_directories
+            .generated_files("completions")?
+            .into_iter()
+            .filter(|file| matches(file))
+            .collect())
+    };
+    let sources = match key {
+        "exe" => rust_directories.cargo_targets(pkg_name, "bin")?,
+        "libs" => {
+            let mut libs = rust_directories.cargo_targets(pkg_name, "cdylib")?;
+            libs.extend(rust_directories.cargo_targets(pkg_name, "staticlib")?);
+            libs
+        }
+        // The man pages generated by clap_mangen, i.e. in rinstall build.rs
+        "man" => rust_directories
+            .generated_files("man")?
+            .into_iter()
+            .filter(|file| {
+                file.extension()
+                    .is_some_and(|ext| ext.starts_with(|c: char| c.is_ascii_digit()))
+            })
+            .collect(),
+        "completions:bash" => completions(|file| file.extension() == Some("bash"))?,
+        "completions:elvish" => completions(|file| file.extension() == Some("elv"))?,
+        "completions:fish" => completions(|file| file.extension() == Some("fish"))?,
+        "completions:zsh" => completions(|file| {
+            file.extension().is_none() && file.file_name().is_some_and(|name| name.starts_with('_'))
+        })?,
+        _ => unreachable!(),
+    };
+    if sources.is_empty() && !key.starts_with("completions:") {
+        warn!(
+            "no files found for '{}' in package {}, has the project been built?",
+            format!("{key}: auto").bright_black(),
+            pkg_name.blue().italic()
+        );
+    }
+
+    Ok(sources)
+}
+
 // DataEntry is not really a good name, it is just an Entry with use_pkg_name option
 #[derive(Deserialize)]
 #[serde(transparent)]
@@ -234,6 +421,8 @@ struct Completions {
     fish: Vec<Entry>,
     #[serde(default)]
     zsh: Vec<Entry>,
+    // The command printing the completions, instead of the files listed above
+    generate: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -242,18 +431,27 @@ pub struct Package {
     pub name: Option<String>,
     #[serde(rename(deserialize = "type"), default)]
     pub pkg_type: Type,
+    // Metadata used when building distribution packages
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub maintainer: Option<String>,
+    pub homepage: Option<String>,
+    pub license: Option<String>,
+    // The shell commands run by `install --build` in the project directory
+    #[serde(default)]
+    pub build: Vec<String>,
     #[serde(default)]
-    exe: Vec<Entry>,
+    exe: MaybeAuto<Vec<Entry>>,
     #[serde(default, rename(deserialize = "admin-exe"))]
     admin_exe: Vec<Entry>,
     #[serde(default)]
-    libs: Vec<Entry>,
+    libs: MaybeAuto<Vec<Entry>>,
     #[serde(default)]
     libexec: Vec<Entry>,
     #[serde(default)]
     includes: Vec<Entry>,
     #[serde(default)]
-    man: Vec<Entry>,
+    man: MaybeAuto<Vec<Entry>>,
     #[serde(default)]
     data: Vec<DataEntry>,
     #[serde(default)]
@@ -267,7 +465,7 @@ pub struct Package {
     #[serde(default, rename(deserialize = "appstream-metadata"))]
     appstream_metadata: Vec<Entry>,
     #[serde(default)]
-    completions: Completions,
+    completions: MaybeAuto<Completions>,
     #[serde(default, rename(deserialize = "pam-modules"))]
     pam_modules: Vec<Entry>,
     #[serde(default, rename(deserialize = "systemd-units"))]
@@ -296,11 +494,59 @@ impl Package {
         self.check_entries(rinstall_version)?;
 
         let package_name = self.name.unwrap();
+        let exe = self.exe.resolve(&package_name, "exe")?;
+        let libs = self.libs.resolve(&package_name, "libs")?;
+        let man = self.man.resolve(&package_name, "man")?;
+        let completions_entries = self.completions.resolve(&package_name)?;
+        // The first executable generates the completions, along with its installed name
+        let generator = exe.first().map(|entry| {
+            let entry = &entry.entry;
+            let name = entry
+                .destination
+                .as_ref()
+                .filter(|destination| !destination.as_str().ends_with('/'))
+                .unwrap_or(&entry.source)
+                .file_name()
+                .unwrap_or_default()
+                .to_string();
+            (self.pkg_type.sourcepath(&entry.source), name)
+        });
+
+        if !system_install {
+            // These have no place in the user directories
+            let skipped = [
+                (
+                    "admin-exe",
+                    !self.admin_exe.is_empty() && dirs.sbindir.is_none(),
+                ),
+                (
+                    "includes",
+                    !self.includes.is_empty() && dirs.includedir.is_none(),
+                ),
+                ("docs", !self.docs.is_empty() && dirs.docdir.is_none()),
+                ("man", !man.is_empty() && dirs.mandir.is_none()),
+                (
+                    "pam-modules",
+                    !self.pam_modules.is_empty() && dirs.pam_modulesdir.is_none(),
+                ),
+                ("pixmaps", self.icons.iter().any(|icon| icon.icon.pixmaps)),
+            ]
+            .into_iter()
+            .filter_map(|(name, skipped)| skipped.then_some(name))
+            .collect::<Vec<_>>();
+            if !skipped.is_empty() {
+                warn!(
+                    "{} of package {} cannot be installed per-user, skipping them",
+                    skipped.join(", ").bright_black(),
+                    package_name.blue().italic()
+                );
+            }
+        }
 
         let empty_path = Utf8PathBuf::new();
         let mut results = [
-            (self.exe, Some(&dirs.bindir), "exe"),
-            (self.libs, Some(&dirs.libdir), "libs"),
+            (exe, Some(&dirs.bindir), "exe"),
+            (libs, Some(&dirs.libdir), "libs"),
             (self.libexec, Some(&dirs.libexecdir), "libexec"),
             (self.admin_exe, dirs.sbindir.as_ref(), "admin_exe"),
             (self.includes, dirs.includedir.as_ref(), "includes"),
@@ -325,10 +571,17 @@ impl Package {
             }
             let dir = dir.unwrap();
             let pkg_type = self.pkg_type;
+            // The executables and libraries can be stripped and checked
+            let kind = match name {
+                "exe" | "libexec" | "admin_exe" => EntryKind::Executable,
+                "libs" => EntryKind::Library,
+                _ => EntryKind::Default,
+            };
             files.into_iter().map(move |entry| {
                 entry
                     .entry
                     .new_entry(FilesPolicy::Replace, dir, &pkg_type)
+                    .map(|entry| entry.with_kind(kind))
                     .with_context(|| format!("While iterating {name} entries"))
             })
         })
@@ -339,28 +592,32 @@ impl Package {
                     Some(&dirs.datadir),
                     "data".to_owned(),
                     FilesPolicy::Replace,
+                    EntryKind::Default,
                 ),
                 (
                     self.config,
                     Some(&dirs.sysconfdir),
                     "config".to_owned(),
                     FilesPolicy::NoReplace,
+                    EntryKind::Default,
                 ),
                 (
                     self.docs,
                     dirs.docdir.as_ref(),
                     "docs".to_owned(),
                     FilesPolicy::Replace,
+                    EntryKind::Docs,
                 ),
                 (
                     self.licenses,
                     Some(&dirs.datarootdir.join("licenses/")),
                     "licenses".to_owned(),
                     FilesPolicy::Replace,
+                    EntryKind::Licenses,
                 ),
             ]
             .into_iter()
-            .flat_map(|(mut files, mut dir, name, policy)| {
+            .flat_map(|(mut files, mut dir, name, policy, kind)| {
                 // Skip directories that don't exists, i.e. mandir, includedir
                 if dir.is_none() {
                     files.clear();
@@ -384,6 +641,7 @@ impl Package {
                     entry
                         .entry
                         .new_entry(policy, dir, pkg_type)
+                        .map(|entry| entry.with_kind(kind))
                         .with_context(|| format!("While iterating {name} entries"))
                 })
             }),
@@ -392,10 +650,9 @@ impl Package {
 
         if let Some(mandir) = &dirs.mandir {
             results.extend(
-                self.man
-                    .into_iter()
+                man.into_iter()
                     .map(|entry| -> Result<InstallEntry> {
-                        let entry = entry.entry;
+                        let mut entry = entry.entry;
                         ensure!(
                             !entry.source.as_str().ends_with('/'),
                             "the man entry cannot be a directory"
@@ -403,13 +660,20 @@ impl Package {
                         let use_source_name = entry
                             .destination
                             .as_ref()
-                            .map_or(true, |destination| destination.as_str().ends_with('/'));
-                        let name = if use_source_name {
-                            &entry.source
-                        } else {
-                            entry.destination.as_ref().unwrap()
-                        };
-                        let man_cat = name
+                            .is_none_or(|destination| destination.as_str().ends_with('/'));
+                        // The rendered man pages lose their extension, i.e. foo.1.scd -> foo.1
+                        if use_source_name && ManFormat::from_path(&entry.source).is_some() {
+                            let page = entry.source.file_stem().unwrap();
+                            entry.destination =
+                                Some(entry.destination.unwrap_or_default().join(page));
+                        }
+                        let name = entry
+                            .destination
+                            .as_ref()
+                            .filter(|destination| !destination.as_str().ends_with('/'))
+                            .unwrap_or(&entry.source);
+                        // The page could be already compressed, i.e. foo.1.gz
+                        let man_cat = Utf8Path::new(page_name(name.as_str()))
                             .extension()
                             .with_context(|| format!("unable to get extension of file {:?}", name))?
                             .to_string();
@@ -418,7 +682,17 @@ impl Package {
                             "the last character should be a digit from 1 to 8"
                         );
                         let install_dir = mandir.join(format!("man{}/", &man_cat));
-                        entry.new_entry(FilesPolicy::Replace, &install_dir, &self.pkg_type)
+                        let mut entry = entry
+                            .new_entry(FilesPolicy::Replace, &install_dir, &self.pkg_type)?
+                            .with_kind(EntryKind::Man);
+                        // The man pages are compressed when installing them
+                        if let Some(extension) = dirs.man_compression.extension() {
+                            entry.destination = Utf8PathBuf::from(format!(
+                                "{}.{extension}",
+                                entry.destination_for_file()
+                            ));
+                        }
+                        Ok(entry)
                     })
                     .collect::<Result<Vec<InstallEntry>>>()
                     .context("error while iterating man pages")?,
@@ -426,19 +700,25 @@ impl Package {
         }
 
         if system_install {
-            results.extend(
-                self.user_config
-                    .into_iter()
-                    .map(|entry| {
-                        entry.entry.new_entry(
-                            FilesPolicy::Replace,
-                            &dirs.docdir.as_ref().unwrap().join("user-config"),
-                            &self.pkg_type,
-                        )
-                    })
-                    .collect::<Result<Vec<InstallEntry>>>()
-                    .context("error while iterating user-config entries")?,
-            );
+            // Treated as documentation, skipped along with it when there is no docdir
+            if let Some(docdir) = &dirs.docdir {
+                results.extend(
+                    self.user_config
+                        .into_iter()
+                        .map(|entry| {
+                            entry
+                                .entry
+                                .new_entry(
+                                    FilesPolicy::Replace,
+                                    &docdir.join("user-config"),
+                                    &self.pkg_type,
+                                )
+                                .map(|entry| entry.with_kind(EntryKind::Docs))
+                        })
+                        .collect::<Result<Vec<InstallEntry>>>()
+                        .context("error while iterating user-config entries")?,
+                );
+            }
         } else {
             results.extend(
                 self.user_config
@@ -455,66 +735,113 @@ impl Package {
             );
         }
 
-        if system_install {
-            results.extend(
-                self.appstream_metadata
-                    .into_iter()
-                    .map(|entry| {
-                        entry.entry.new_entry(
-                            FilesPolicy::Replace,
-                            &dirs.datarootdir.join("metainfo/"),
-                            &self.pkg_type,
-                        )
-                    })
-                    .collect::<Result<Vec<InstallEntry>>>()
-                    .context("error while iterating appstream-metadata entries")?,
-            );
+        results.extend(
+            self.appstream_metadata
+                .into_iter()
+                .map(|entry| {
+                    entry.entry.new_entry(
+                        FilesPolicy::Replace,
+                        &dirs.datarootdir.join("metainfo/"),
+                        &self.pkg_type,
+                    )
+                })
+                .collect::<Result<Vec<InstallEntry>>>()
+                .context("error while iterating appstream-metadata entries")?,
+        );
+
+        let bash_completionsdir = if system_install {
+            "bash-completion/completions/"
+        } else {
+            "bash-completion/"
+        };
+        if let Some(generate) = &completions_entries.generate {
+            let (bin_path, name) = generator.as_ref().context(
+                "the completions are generated by the first exe entry, but there is none",
+            )?;
+            // The command runs in the package directory, which can differ from the current one;
+            // the executable is missing when installing from a tarball, where it is not run
+            let bin_path = bin_path
+                .canonicalize_utf8()
+                .unwrap_or_else(|_| bin_path.clone());
+            let bin = format!("'{}'", bin_path.as_str().replace('\'', r"'\''"));
+            let shells = [
+                (
+                    "bash",
+                    completions_to_install.bash,
+                    bash_completionsdir,
+                    name.clone(),
+                ),
+                (
+                    "elvish",
+                    completions_to_install.elvish,
+                    "elvish/lib/",
+                    format!("{name}.elv"),
+                ),
+                (
+                    "fish",
+                    completions_to_install.fish,
+                    "fish/vendor_completions.d/",
+                    format!("{name}.fish"),
+                ),
+                (
+                    "zsh",
+                    completions_to_install.zsh,
+                    "zsh/site-functions/",
+                    format!("_{name}"),
+                ),
+            ];
+            for (shell, enabled, completionsdir, file_name) in shells {
+                if !enabled {
+                    continue;
+                }
+                results.push(InstallEntry {
+                    // The path of the generated file inside the tarballs
+                    source: Utf8Path::new(".completions").join(shell).join(&file_name),
+                    full_source: bin_path.clone(),
+                    destination: dirs.datarootdir.join(completionsdir).join(file_name),
+                    templating: false,
+                    strip: false,
+                    shebang: None,
+                    generate: Some(generate.replace("{bin}", &bin).replace("{shell}", shell)),
+                    replace: true,
+                    kind: EntryKind::Default,
+                    pkg_type: self.pkg_type,
+                });
+            }
         }
 
         let mut completions = Vec::new();
         if completions_to_install.bash {
             completions.extend(
-                self.completions
+                completions_entries
                     .bash
                     .into_iter()
-                    .map(|completion| {
-                        (
-                            completion,
-                            if system_install {
-                                "bash-completion/completions/"
-                            } else {
-                                "bash-completion/"
-                            },
-                        )
-                    })
-                    .collect::<Vec<(Entry, &'static str)>>(),
-            )
+                    .map(|completion| (completion, bash_completionsdir)),
+            );
         }
         if completions_to_install.elvish {
             completions.extend(
-                self.completions
+                completions_entries
                     .elvish
                     .into_iter()
                     .map(|completion| (completion, "elvish/lib/")),
             );
         }
-        if system_install {
-            if completions_to_install.fish {
-                completions.extend(
-                    self.completions
-                        .fish
-                        .into_iter()
-                        .map(|completion| (completion, "fish/vendor_completions.d/")),
-                );
-            }
-            if completions_to_install.zsh {
-                completions.extend(
-                    self.completions
-                        .zsh
-                        .into_iter()
-                        .map(|completion| (completion, "zsh/site-functions/")),
-                );
-            }
+        if completions_to_install.fish {
+            completions.extend(
+                completions_entries
+                    .fish
+                    .into_iter()
+                    .map(|completion| (completion, "fish/vendor_completions.d/")),
+            );
+        }
+        if completions_to_install.zsh {
+            completions.extend(
+                completions_entries
+                    .zsh
+                    .into_iter()
+                    .map(|completion| (completion, "zsh/site-functions/")),
+            );
         }
         results.extend(
             completions
@@ -542,6 +869,8 @@ impl Package {
                                     source,
                                     destination,
                                     templating,
+                                    strip,
+                                    shebang,
                                 },
                         } = entry;
 
@@ -559,33 +888,32 @@ impl Package {
                             source,
                             destination,
                             templating,
+                            strip,
+                            shebang,
                         }
-                        .new_entry(
-                            FilesPolicy::Replace,
-                            pam_modulesdir,
-                            &self.pkg_type,
-                        )
+                        .new_entry(FilesPolicy::Replace, pam_modulesdir, &self.pkg_type)
+                        .map(|entry| entry.with_kind(EntryKind::PamModule))
                     })
                     .collect::<Result<Vec<InstallEntry>>>()
                     .context("error while iterating pam-modules")?,
             );
         }
 
-        if system_install {
-            results.extend(
-                self.systemd_units
-                    .into_iter()
-                    .map(|entry| {
-                        entry.entry.new_entry(
-                            FilesPolicy::Replace,
-                            &dirs.systemd_unitsdir.join("system/"),
-                            &self.pkg_type,
-                        )
-                    })
-                    .collect::<Result<Vec<InstallEntry>>>()
-                    .context("error while iterating systemd-units entries")?,
-            );
-        }
+        // The units run by the user instance of systemd when installing per-user
+        let systemd_unitsdir =
+            dirs.systemd_unitsdir
+                .join(if system_install { "system/" } else { "user/" });
+        results.extend(
+            self.systemd_units
+                .into_iter()
+                .map(|entry| {
+                    entry
+                        .entry
+                        .new_entry(FilesPolicy::Replace, &systemd_unitsdir, &self.pkg_type)
+                })
+                .collect::<Result<Vec<InstallEntry>>>()
+                .context("error while iterating systemd-units entries")?,
+        );
 
         results.extend(
             self.icons
@@ -602,6 +930,8 @@ impl Package {
                             )
                         })?),
                         templating: false,
+                        strip: false,
+                        shebang: None,
                     }
                     .new_entry(
                         FilesPolicy::Replace,
@@ -613,56 +943,100 @@ impl Package {
                 .context("error while iterating icons")?,
         );
 
-        if system_install {
-            results.extend(
-                self.terminfo
-                    .into_iter()
-                    .map(|entry| -> Result<InstallEntry> {
-                        let entry = entry.entry;
-                        ensure!(
-                            !entry.source.as_str().ends_with('/'),
-                            "the terminfo entry cannot be a directory"
-                        );
-                        let use_source_name = entry
-                            .destination
-                            .as_ref()
-                            .map_or(true, |destination| destination.as_str().ends_with('/'));
-                        let name = if use_source_name {
-                            &entry.source
-                        } else {
-                            entry.destination.as_ref().unwrap()
-                        };
-                        let initial = name
-                            .file_name()
-                            .with_context(|| format!("unable to get filename of file {:?}", name))?
-                            .chars()
-                            .next()
-                            .with_context(|| {
-                                format!("terminfo entry {:?} contains an empty filename", name)
-                            })?
-                            .to_lowercase()
-                            .to_string();
-                        let install_dir = dirs.datarootdir.join("terminfo").join(initial);
-                        entry.new_entry(FilesPolicy::Replace, &install_dir, &self.pkg_type)
-                    })
-                    .collect::<Result<Vec<InstallEntry>>>()
-                    .context("error while iterating terminfo files")?,
+        // ncurses only looks for the user entries in ~/.terminfo
+        let terminfodir = if system_install {
+            dirs.datarootdir.join("terminfo")
+        } else {
+            Utf8PathBuf::from(env::var("HOME").context("unable to get the home directory")?)
+                .join(".terminfo")
+        };
+        results.extend(
+            self.terminfo
+                .into_iter()
+                .map(|entry| -> Result<InstallEntry> {
+                    let entry = entry.entry;
+                    ensure!(
+                        !entry.source.as_str().ends_with('/'),
+                        "the terminfo entry cannot be a directory"
+                    );
+                    let use_source_name = entry
+                        .destination
+                        .as_ref()
+                        .is_none_or(|destination| destination.as_str().ends_with('/'));
+                    let name = if use_source_name {
+                        &entry.source
+                    } else {
+                        entry.destination.as_ref().unwrap()
+                    };
+                    let initial = name
+                        .file_name()
+                        .with_context(|| format!("unable to get filename of file {:?}", name))?
+                        .chars()
+                        .next()
+                        .with_context(|| {
+                            format!("terminfo entry {:?} contains an empty filename", name)
+                        })?
+                        .to_lowercase()
+                        .to_string();
+                    let install_dir = terminfodir.join(initial);
+                    entry.new_entry(FilesPolicy::Replace, &install_dir, &self.pkg_type)
+                })
+                .collect::<Result<Vec<InstallEntry>>>()
+                .context("error while iterating terminfo files")?,
+        );
+
+        results.extend(
+            self.pkg_config
+                .into_iter()
+                .map(|entry| {
+                    entry.entry.new_entry(
+                        FilesPolicy::Replace,
+                        &dirs.libdir.join("pkgconfig/"),
+                        &self.pkg_type,
+                    )
+                })
+                .collect::<Result<Vec<InstallEntry>>>()
+                .context("error while iterating pkg-config entries")?,
+        );
+
+        let requires = VersionReq::parse(">=0.4.0").unwrap();
+        ensure!(
+            !results.iter().any(|entry| entry.strip)
+                || requires.matches(&Version::from(rinstall_version)),
+            "strip requires version {requires}"
+        );
+        ensure!(
+            !results.iter().any(|entry| entry.shebang.is_some())
+                || requires.matches(&Version::from(rinstall_version)),
+            "shebang requires version {requires}"
+        );
+        if let Some(entry) = results.iter().find(|entry| {
+            entry.strip && !matches!(entry.kind, EntryKind::Executable | EntryKind::Library)
+        }) {
+            bail!(
+                "{:?} cannot be stripped, only exe, admin-exe, libexec and libs entries can",
+                entry.source
             );
         }
-
-        if system_install {
-            results.extend(
-                self.pkg_config
-                    .into_iter()
-                    .map(|entry| {
-                        entry.entry.new_entry(
-                            FilesPolicy::Replace,
-                            &dirs.libdir.join("pkgconfig/"),
-                            &self.pkg_type,
-                        )
-                    })
-                    .collect::<Result<Vec<InstallEntry>>>()
-                    .context("error while iterating pkg-config entries")?,
+        ensure!(
+            !results.iter().any(|entry| {
+                entry.kind == EntryKind::Man && ManFormat::from_path(&entry.source).is_some()
+            }) || requires.matches(&Version::from(rinstall_version)),
+            "rendering the scdoc and Markdown man pages requires version {requires}"
+        );
+        for entry in &results {
+            let Some(shebang) = &entry.shebang else {
+                continue;
+            };
+            ensure!(
+                entry.kind == EntryKind::Executable,
+                "the shebang of {:?} cannot be rewritten, only exe, admin-exe and libexec entries can",
+                entry.source
+            );
+            ensure!(
+                !shebang.is_empty() && !shebang.contains('\n'),
+                "the shebang of {:?} must be a single non-empty line",
+                entry.source
             );
         }
 
@@ -699,6 +1073,13 @@ impl Package {
                 "default".bright_black(),
             );
         }
+        ensure!(
+            !matches!(self.pkg_type, Type::Meson | Type::CMake | Type::Go)
+                || VersionReq::parse(">=0.4.0").unwrap().matches(&version),
+            "type '{}' requires version >=0.4.0",
+            format!("{:?}", self.pkg_type).to_lowercase()
+        );
+        check_version!("build", build, ">=0.4.0");
         check_version!("exe", exe, ">=0.1.0");
         check_version!("admin_exe", admin_exe, ">=0.1.0");
         check_version!("libs", libs, ">=0.1.0");
@@ -711,10 +1092,25 @@ impl Package {
         check_version!("user-config", user_config, ">=0.1.0");
         check_version!("desktop-files", desktop_files, ">=0.1.0");
         check_version!("appstream-metadata", appstream_metadata, ">=0.1.0");
-        check_version_expr!("completions:bash", self.completions.bash, ">=0.1.0");
-        check_version_expr!("completions:elvish", self.completions.elvish, ">=0.2.0");
-        check_version_expr!("completions:fish", self.completions.fish, ">=0.1.0");
-        check_version_expr!("completions:zsh", self.completions.zsh, ">=0.1.0");
+        if let MaybeAuto::Entries(completions) = &self.completions {
+            check_version_expr!("completions:bash", completions.bash, ">=0.1.0");
+            check_version_expr!("completions:elvish", completions.elvish, ">=0.2.0");
+            check_version_expr!("completions:fish", completions.fish, ">=0.1.0");
+            check_version_expr!("completions:zsh", completions.zsh, ">=0.1.0");
+            check_version_expr!(
+                "completions:generate",
+                completions.generate.as_slice(),
+                ">=0.4.0"
+            );
+            ensure!(
+                completions.generate.is_none()
+                    || completions.bash.is_empty()
+                        && completions.elvish.is_empty()
+                        && completions.fish.is_empty()
+                        && completions.zsh.is_empty(),
+                "the completions cannot be both generated and listed"
+            );
+        }
         check_version!("pam-modules", pam_modules, ">=0.1.0");
         check_version!("systemd-units", systemd_units, ">=0.1.0");
         check_version!("systemd-user-units", systemd_user_units, ">=0.2.0");
@@ -722,6 +1118,27 @@ impl Package {
         check_version!("terminfo", terminfo, ">=0.1.0");
         check_version!("licenses", licenses, ">=0.1.0");
         check_version!("pkg-config", pkg_config, ">=0.1.0");
+        check_version_expr!("version", self.version.as_slice(), ">=0.4.0");
+        check_version_expr!("description", self.description.as_slice(), ">=0.4.0");
+        check_version_expr!("maintainer", self.maintainer.as_slice(), ">=0.4.0");
+        check_version_expr!("homepage", self.homepage.as_slice(), ">=0.4.0");
+        check_version_expr!("license", self.license.as_slice(), ">=0.4.0");
+
+        let auto_entries = [
+            ("exe", matches!(self.exe, MaybeAuto::Auto)),
+            ("libs", matches!(self.libs, MaybeAuto::Auto)),
+            ("man", matches!(self.man, MaybeAuto::Auto)),
+            ("completions", matches!(self.completions, MaybeAuto::Auto)),
+        ]
+        .into_iter()
+        .filter_map(|(name, auto)| auto.then_some(name))
+        .collect::<Vec<_>>();
+        check_version_expr!("auto", auto_entries, ">=0.4.0");
+        ensure!(
+            auto_entries.is_empty() || self.pkg_type == Type::Rust,
+            "{} can only be set to auto in rust packages",
+            auto_entries.join(", ")
+        );
 
         Ok(())
     }
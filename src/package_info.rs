@@ -4,7 +4,10 @@ use std::{
 };
 
 use camino::{Utf8Path, Utf8PathBuf};
-use color_eyre::{eyre::Context, Result};
+use color_eyre::{
+    eyre::{ensure, Context},
+    Result,
+};
 use serde::{Deserialize, Serialize};
 
 use crate::dirs::Dirs;
@@ -33,6 +36,17 @@ pub struct PackageInfo {
     #[serde(skip)]
     pub pkg_name: String,
     pub path: Utf8PathBuf,
+    // The directories used when installing the package, it is missing
+    // in the pkginfo installed by older versions of rinstall
+    #[serde(default)]
+    pub dirs: Option<Dirs>,
+    // The metadata of the package, when written in install.yml
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
     pub files: Vec<InstalledFile>,
 }
 
@@ -47,10 +61,37 @@ impl PackageInfo {
                 .localstatedir
                 .join("rinstall")
                 .join(format!("{}.pkg", &pkg_name)),
+            dirs: Some(dirs.clone()),
+            version: None,
+            homepage: None,
+            license: None,
             files: Vec::new(),
         }
     }
 
+    /// Load the pkginfo of an installed package, either from its name or from
+    /// the path of the pkginfo file
+    pub fn load(
+        pkg: &str,
+        dirs: &Dirs,
+    ) -> Result<Self> {
+        let pkg_info = if Utf8Path::new(pkg).is_absolute() {
+            Utf8PathBuf::from(pkg)
+        } else {
+            dirs.localstatedir
+                .join("rinstall")
+                .join(format!("{}.pkg", pkg))
+        };
+        ensure!(pkg_info.exists(), "package {} is not installed", pkg);
+        let mut pkg_info: Self = serde_yaml::from_str(
+            &fs::read_to_string(&pkg_info)
+                .with_context(|| format!("unable to read file {:?}", &pkg_info))?,
+        )?;
+        pkg_info.pkg_name = pkg_info.path.file_stem().unwrap_or(pkg).to_string();
+
+        Ok(pkg_info)
+    }
+
     pub fn add_file(
         &mut self,
         target_path: &Utf8Path,
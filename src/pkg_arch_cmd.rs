@@ -0,0 +1,70 @@
+#[derive(Args, Clone)]
+pub struct PkgArchCmd {
+    #[clap(
+        long,
+        help = concat!("Use the generated binaries and libraries from the",
+                       " debug profile (only effective for rust projects)")
+    )]
+    pub rust_debug_target: bool,
+    #[clap(
+        long,
+        help = concat!("Use the generated binaries and libraries from this",
+                       " cargo profile, `dev` uses the debug directory",
+                       " (only effective for rust projects)"),
+        conflicts_with = "rust_debug_target"
+    )]
+    pub rust_profile: Option<String>,
+    #[clap(
+        long,
+        help = concat!("Use the generated binaries and libraries from this",
+                       " target triple (only effective for rust projects)"),
+        env = "CARGO_BUILD_TARGET"
+    )]
+    pub rust_target_triple: Option<String>,
+    #[clap(
+        short = 'P',
+        long,
+        help = "Path to the directory containing the project to package",
+        default_value_os_t = std::env::current_dir()
+            .expect("unable to get current directory"),
+    )]
+    pub package_dir: std::path::PathBuf,
+    #[clap(
+        long,
+        help = concat!("Path to the build directory of meson, cmake and go projects,",
+                       " relative to the current directory")
+    )]
+    pub build_dir: Option<String>,
+    #[clap(
+        short = 'p',
+        long = "pkgs",
+        help = "List of packages to build, separated by a comma"
+    )]
+    pub packages: Vec<String>,
+    #[clap(long, help = "Version of the package, overriding the one in install.yml")]
+    pub pkg_version: Option<String>,
+    #[clap(
+        long,
+        help = "Description of the package, overriding the one in install.yml"
+    )]
+    pub description: Option<String>,
+    #[clap(
+        long,
+        help = "Packager of the package, overriding the maintainer in install.yml"
+    )]
+    pub packager: Option<String>,
+    #[clap(
+        long,
+        help = concat!("Arch Linux architecture of the package",
+                       " [default: the architecture of the target triple or of the host]")
+    )]
+    pub architecture: Option<String>,
+    #[clap(
+        short = 'o',
+        long,
+        help = "Directory where the packages are written",
+        default_value_os_t = std::env::current_dir()
+            .expect("unable to get current directory"),
+    )]
+    pub output_dir: std::path::PathBuf,
+}
@@ -0,0 +1,319 @@
+use std::{
+    fmt::Write as _,
+    fs,
+    io::Write,
+    os::unix::fs::{MetadataExt, PermissionsExt},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use camino::Utf8Path;
+use clap::Args;
+use color_eyre::{
+    eyre::{bail, ensure, Context, ContextCompat},
+    Result,
+};
+use colored::Colorize;
+use flate2::{write::GzEncoder, Compression};
+use log::info;
+use md5::Md5;
+use ruzstd::encoding::{compress_to_vec, CompressionLevel};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    install_spec::InstallSpec,
+    staging::{BuildOptions, StagedPackage},
+    tarball::append_data,
+};
+
+include!("pkg_arch_cmd.rs");
+
+// The fields of .PKGINFO
+struct PkgInfo {
+    pkgname: String,
+    pkgver: String,
+    pkgrel: String,
+    pkgdesc: Option<String>,
+    url: Option<String>,
+    packager: Option<String>,
+    license: Option<String>,
+    arch: String,
+}
+
+impl PkgInfo {
+    fn validate(&self) -> Result<()> {
+        ensure!(
+            !self.pkgname.is_empty()
+                && !self.pkgname.starts_with(['-', '.'])
+                && self.pkgname.chars().all(|c| {
+                    c.is_ascii_lowercase()
+                        || c.is_ascii_digit()
+                        || matches!(c, '@' | '.' | '_' | '+' | '-')
+                }),
+            concat!(
+                "{} is not a valid Arch Linux package name, it must only contain lowercase",
+                " letters, digits and the characters @ . _ + -"
+            ),
+            self.pkgname
+        );
+        ensure!(
+            !self.pkgver.is_empty()
+                && self
+                    .pkgver
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '+' | ':')),
+            "{} is not a valid Arch Linux version",
+            self.pkgver
+        );
+        ensure!(
+            !self.pkgrel.is_empty() && self.pkgrel.chars().all(|c| c.is_ascii_digit() || c == '.'),
+            "{} is not a valid Arch Linux release, it must be a number",
+            self.pkgrel
+        );
+
+        Ok(())
+    }
+
+    fn render(
+        &self,
+        size: u64,
+        builddate: u64,
+        backup: &[&str],
+    ) -> String {
+        let mut res = format!("# Generated by rinstall {}\n", env!("CARGO_PKG_VERSION"));
+        writeln!(res, "pkgname = {}", self.pkgname).unwrap();
+        writeln!(res, "pkgbase = {}", self.pkgname).unwrap();
+        writeln!(res, "pkgver = {}-{}", self.pkgver, self.pkgrel).unwrap();
+        if let Some(pkgdesc) = &self.pkgdesc {
+            // Only the first line is used as description
+            writeln!(
+                res,
+                "pkgdesc = {}",
+                pkgdesc.trim().lines().next().unwrap_or_default()
+            )
+            .unwrap();
+        }
+        if let Some(url) = &self.url {
+            writeln!(res, "url = {url}").unwrap();
+        }
+        writeln!(res, "builddate = {builddate}").unwrap();
+        if let Some(packager) = &self.packager {
+            writeln!(res, "packager = {packager}").unwrap();
+        }
+        writeln!(res, "size = {size}").unwrap();
+        writeln!(res, "arch = {}", self.arch).unwrap();
+        if let Some(license) = &self.license {
+            writeln!(res, "license = {license}").unwrap();
+        }
+        for file in backup {
+            writeln!(res, "backup = {file}").unwrap();
+        }
+
+        res
+    }
+
+    // The epoch is not part of the filename
+    fn filename(&self) -> String {
+        let pkgver = self
+            .pkgver
+            .split_once(':')
+            .map_or(self.pkgver.as_str(), |(_, pkgver)| pkgver);
+        format!(
+            "{}-{pkgver}-{}-{}.pkg.tar.zst",
+            self.pkgname, self.pkgrel, self.arch
+        )
+    }
+}
+
+/// Returns the Arch Linux architecture of the target triple, or of the host
+fn arch_architecture(rust_target_triple: Option<&str>) -> Result<String> {
+    let (arch, hard_float) = if let Some(triple) = rust_target_triple {
+        (triple.split('-').next().unwrap(), triple.ends_with("hf"))
+    } else {
+        (std::env::consts::ARCH, cfg!(target_abi = "eabihf"))
+    };
+
+    Ok(match arch {
+        "x86_64" => "x86_64",
+        "aarch64" => "aarch64",
+        "x86" | "i386" | "i586" | "i686" => "i686",
+        arch if arch.starts_with("arm") && hard_float => "armv7h",
+        "riscv64" | "riscv64gc" => "riscv64",
+        "loongarch64" => "loong64",
+        _ => bail!("unable to detect the Arch Linux architecture of {arch}, use --architecture"),
+    }
+    .to_string())
+}
+
+// Escape the characters not allowed in mtree paths, as done by libarchive
+fn mtree_escape(path: &str) -> String {
+    let mut res = String::new();
+    for byte in path.bytes() {
+        if byte.is_ascii_graphic() && byte != b'\\' && byte != b'#' {
+            res.push(byte as char);
+        } else {
+            write!(res, "\\{byte:03o}").unwrap();
+        }
+    }
+
+    res
+}
+
+// The files checksums, as stored in .MTREE
+fn mtree_digests(contents: &[u8]) -> String {
+    format!(
+        "size={} md5digest={:x} sha256digest={:x}",
+        contents.len(),
+        Md5::digest(contents),
+        Sha256::digest(contents)
+    )
+}
+
+/// Generate the gzip compressed .MTREE file, used by pacman to check the installed files
+fn mtree(
+    staged: &StagedPackage,
+    pkginfo: &str,
+    builddate: u64,
+) -> Result<Vec<u8>> {
+    let mut res = String::from("#mtree\n/set type=file uid=0 gid=0 mode=644\n");
+    writeln!(
+        res,
+        "./.PKGINFO time={builddate}.0 {}",
+        mtree_digests(pkginfo.as_bytes())
+    )
+    .unwrap();
+    for entry in staged.entries()? {
+        let path = staged.root().join(&entry);
+        let metadata = fs::symlink_metadata(&path)
+            .with_context(|| format!("unable to read metadata of {path}"))?;
+        let mode = metadata.permissions().mode() & 0o7777;
+        write!(
+            res,
+            "./{} time={}.0",
+            mtree_escape(entry.as_str()),
+            metadata.mtime()
+        )
+        .unwrap();
+        if metadata.is_dir() {
+            write!(res, " mode={mode:o} type=dir").unwrap();
+        } else if metadata.is_symlink() {
+            let target =
+                fs::read_link(&path).with_context(|| format!("unable to read symlink {path}"))?;
+            write!(
+                res,
+                " mode={mode:o} type=link link={}",
+                mtree_escape(&target.to_string_lossy())
+            )
+            .unwrap();
+        } else {
+            if mode != 0o644 {
+                write!(res, " mode={mode:o}").unwrap();
+            }
+            let contents =
+                fs::read(&path).with_context(|| format!("unable to read file {path}"))?;
+            write!(res, " {}", mtree_digests(&contents)).unwrap();
+        }
+        res.push('\n');
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(res.as_bytes())
+        .context("unable to compress .MTREE")?;
+    encoder.finish().context("unable to compress .MTREE")
+}
+
+fn write_pkg(
+    pkginfo: &PkgInfo,
+    staged: &StagedPackage,
+    filename: &Utf8Path,
+) -> Result<()> {
+    let builddate = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    // The paths in backup are relative to the root
+    let backup = staged
+        .manifest
+        .config_files()
+        .map(|path| path.as_str().trim_start_matches('/'))
+        .collect::<Vec<_>>();
+    let pkginfo = pkginfo.render(staged.installed_size()?, builddate, &backup);
+
+    // The metadata files come before the files of the package
+    let mut archive = tar::Builder::new(Vec::new());
+    append_data(&mut archive, Utf8Path::new(".PKGINFO"), pkginfo.as_bytes())?;
+    append_data(
+        &mut archive,
+        Utf8Path::new(".MTREE"),
+        &mtree(staged, &pkginfo, builddate)?,
+    )?;
+    staged.append_to_archive(&mut archive)?;
+
+    let archive = archive.into_inner().context("unable to create package")?;
+    fs::write(
+        filename,
+        compress_to_vec(archive.as_slice(), CompressionLevel::Fastest),
+    )
+    .with_context(|| format!("unable to write package {filename}"))
+}
+
+impl PkgArchCmd {
+    pub fn run(self) -> Result<()> {
+        let package_dir = Utf8Path::from_path(&self.package_dir)
+            .with_context(|| format!("{:?} contains invalid UTF-8 characters", self.package_dir))?;
+        let output_dir = Utf8Path::from_path(&self.output_dir)
+            .with_context(|| format!("{:?} contains invalid UTF-8 characters", self.output_dir))?;
+        let arch = if let Some(architecture) = &self.architecture {
+            architecture.clone()
+        } else {
+            arch_architecture(self.rust_target_triple.as_deref())?
+        };
+        // Arch Linux does not use /usr/sbin and /usr/libexec
+        let mut dirs = StagedPackage::system_dirs();
+        dirs.sbindir = Some("/usr/bin".to_string());
+        dirs.libexecdir = Some("/usr/lib".to_string());
+
+        let install_spec = InstallSpec::new_from_path(package_dir)?;
+        for package in install_spec.packages(&self.packages) {
+            let name = package.name.unwrap();
+            let version = self
+                .pkg_version
+                .clone()
+                .or(package.version)
+                .with_context(|| {
+                    format!(
+                        "package {name} has no version, add it to install.yml or use --pkg-version"
+                    )
+                })?;
+            // The release is optional, i.e. 1.0.0-2
+            let (pkgver, pkgrel) = version.rsplit_once('-').unwrap_or((&version, "1"));
+            let pkginfo = PkgInfo {
+                pkgver: pkgver.to_string(),
+                pkgrel: pkgrel.to_string(),
+                pkgdesc: self.description.clone().or(package.description),
+                url: package.homepage,
+                packager: self.packager.clone().or(package.maintainer),
+                license: package.license,
+                arch: arch.clone(),
+                pkgname: name,
+            };
+            pkginfo.validate()?;
+
+            let staged = StagedPackage::new(
+                dirs.clone(),
+                self.package_dir.clone(),
+                &pkginfo.pkgname,
+                BuildOptions {
+                    rust_debug_target: self.rust_debug_target,
+                    rust_profile: self.rust_profile.clone(),
+                    rust_target_triple: self.rust_target_triple.clone(),
+                    build_dir: self.build_dir.clone(),
+                },
+            )?;
+            let filename = output_dir.join(pkginfo.filename());
+            info!("Writing {}", filename.as_str().cyan().bold());
+            write_pkg(&pkginfo, &staged, &filename)?;
+        }
+
+        Ok(())
+    }
+}
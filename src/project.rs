@@ -1,10 +1,74 @@
-use std::{env, os::unix::process::CommandExt, process::Command, sync::Once};
+use std::{
+    env,
+    ffi::{CStr, OsStr},
+    fs,
+    os::unix::{ffi::OsStrExt, process::CommandExt},
+    process::{Command, Stdio},
+    ptr::addr_of,
+};
 
 use camino::{Utf8Path, Utf8PathBuf};
-use color_eyre::{eyre::Context, Result};
+use color_eyre::{
+    eyre::{bail, ensure, Context, ContextCompat},
+    Result,
+};
+use colored::Colorize;
+use json::JsonValue;
+use log::info;
+
+use crate::package::Type;
 
-pub static RUST_DIRECTORIES_ONCE: Once = Once::new();
 pub static mut RUST_DIRECTORIES: Option<RustDirectories> = None;
+pub static mut DEFAULT_DIRECTORIES: Option<DefaultProjectDirectories> = None;
+pub static mut BUILD_DIRECTORIES: Vec<(Type, BuildDirectories)> = Vec::new();
+
+/// Set the directories used to resolve the sources of rust packages
+pub fn set_rust_directories(rust_directories: RustDirectories) {
+    // rinstall is single threaded and the directories are only read while
+    // generating the install entries, never while they are being replaced
+    unsafe {
+        RUST_DIRECTORIES = Some(rust_directories);
+    }
+}
+
+/// Set the directories used to resolve the sources of default packages
+pub fn set_default_directories(default_directories: DefaultProjectDirectories) {
+    // Same as set_rust_directories
+    unsafe {
+        DEFAULT_DIRECTORIES = Some(default_directories);
+    }
+}
+
+/// Set the directories used to resolve the sources of the packages built by
+/// meson, cmake and go, only for the types used by the packages
+pub fn set_build_directories(
+    pkg_types: &[Type],
+    package_dir: &Utf8Path,
+    build_dir: Option<&str>,
+) {
+    let build_directories = pkg_types
+        .iter()
+        .filter(|pkg_type| matches!(pkg_type, Type::Meson | Type::CMake | Type::Go))
+        .map(|pkg_type| {
+            (
+                *pkg_type,
+                BuildDirectories::new(*pkg_type, package_dir, build_dir.map(Utf8PathBuf::from)),
+            )
+        })
+        .collect();
+    // Same as set_rust_directories
+    unsafe {
+        BUILD_DIRECTORIES = build_directories;
+    }
+}
+
+/// The build directories of the given package type, if they have been set
+pub fn build_directories(pkg_type: Type) -> Option<&'static BuildDirectories> {
+    unsafe { &*addr_of!(BUILD_DIRECTORIES) }
+        .iter()
+        .find(|(build_type, _)| *build_type == pkg_type)
+        .map(|(_, build_directories)| build_directories)
+}
 
 // Contains data about the project that will be installed
 // It doesn't refer to the system and the actual installation directories
@@ -14,7 +78,9 @@ pub struct RustDirectories {
     pub outputdir: Option<Utf8PathBuf>,
 }
 
-pub struct DefaultProjectDirectories;
+pub struct DefaultProjectDirectories {
+    pub packagedir: Option<Utf8PathBuf>,
+}
 
 pub trait ProjectDirectories {
     fn sourcepath(
@@ -27,6 +93,7 @@ impl RustDirectories {
     pub fn new(
         package_dir: Option<Utf8PathBuf>,
         rust_debug_target: bool,
+        rust_profile: Option<&str>,
         rust_target_triple: Option<&str>,
     ) -> Result<Self> {
         Ok(Self {
@@ -36,6 +103,7 @@ impl RustDirectories {
                 Some(Self::target_dir(
                     &package_dir,
                     rust_debug_target,
+                    rust_profile,
                     rust_target_triple,
                 )?)
             } else {
@@ -47,61 +115,228 @@ impl RustDirectories {
     fn target_dir(
         package_dir: &Utf8Path,
         rust_debug_target: bool,
+        rust_profile: Option<&str>,
         rust_target_triple: Option<&str>,
     ) -> Result<Utf8PathBuf> {
         let env_target_dir = std::env::var("CARGO_TARGET_DIR");
         // if CARGO_TARGET_DIR and target directory do not exist,
         // try reading the "target_directory" from cargo metadata
-        let res = if env_target_dir.is_err()
+        // Directories extracted from a tarball are not cargo projects
+        let is_cargo_project = package_dir.join("Cargo.toml").exists();
+        let res = if !is_cargo_project {
+            package_dir.join("target")
+        } else if env_target_dir.is_err()
             || !package_dir.join("target").exists()
                 // cargo is installed?
                 && Command::new("cargo")
                     .current_dir(package_dir)
                     .output()
-                    .map_or(false, |output| output.status.success())
+                    .is_ok_and(|output| output.status.success())
         {
-            Utf8PathBuf::from(
-                json::parse(&String::from_utf8_lossy(
-                    &Command::new("cargo")
-                        .arg("metadata")
-                        .current_dir(package_dir)
-                        .uid(
-                            // cargo metadata only works when running as the current user that has built
-                            // the project. Otherwise it will use metadata for the root user and
-                            // it is almost never what we want
-                            env::var("SUDO_UID").map_or(unsafe { libc::getuid() }, |uid| {
-                                uid.parse::<u32>().unwrap()
-                            }),
-                        )
-                        .gid(
-                            env::var("SUDO_GID").map_or(unsafe { libc::getgid() }, |gid| {
-                                gid.parse::<u32>().unwrap()
-                            }),
-                        )
-                        .output()
-                        .context("unable to run `cargo metadata`")?
-                        .stdout,
-                ))
-                .context("unable to parse JSON from `cargo metadata` output")?["target_directory"]
-                    .to_string(),
-            )
+            match cargo_metadata(package_dir) {
+                Ok(metadata) => Utf8PathBuf::from(metadata["target_directory"].to_string()),
+                // cargo might not be in the PATH of the root user, assume the
+                // default target directory of the workspace
+                Err(_) if env_target_dir.is_err() => workspace_root(package_dir).join("target"),
+                Err(err) => return Err(err),
+            }
         } else if let Ok(env_target_dir) = env_target_dir {
             Utf8PathBuf::from(env_target_dir)
         } else {
-            package_dir.join("target")
+            workspace_root(package_dir).join("target")
         };
 
-        // Append the target triple and debug/release
+        // Append the target triple and the directory of the profile, cargo
+        // uses "debug" for the dev profile and its test profile
+        let profile_dir = match rust_profile {
+            Some("dev" | "test") => "debug",
+            Some("bench") => "release",
+            Some(rust_profile) => rust_profile,
+            None if rust_debug_target => "debug",
+            None => "release",
+        };
         let res = res
             .join(rust_target_triple.unwrap_or_default())
-            .join(if rust_debug_target {
-                "debug"
-            } else {
-                "release"
-            });
+            .join(profile_dir);
 
         Ok(res)
     }
+
+    fn cargo_package(
+        &self,
+        pkg_name: &str,
+    ) -> Result<JsonValue> {
+        let packagedir = self
+            .packagedir
+            .as_ref()
+            .context("auto entries can only be used when installing from a cargo project")?;
+        let metadata = cargo_metadata(packagedir)?;
+        let manifest_path = packagedir.join("Cargo.toml");
+        // Use the crate named after the package, or the one in the package directory
+        let Some(package) = metadata["packages"]
+            .members()
+            .find(|package| package["name"] == pkg_name)
+            .or_else(|| {
+                metadata["packages"]
+                    .members()
+                    .find(|package| package["manifest_path"] == manifest_path.as_str())
+            })
+        else {
+            bail!("unable to find the crate of package {pkg_name} in `cargo metadata` output");
+        };
+
+        Ok(package.clone())
+    }
+
+    /// The files generated by cargo for the targets of the given kinds,
+    /// i.e. the binaries for "bin"
+    pub fn cargo_targets(
+        &self,
+        pkg_name: &str,
+        kind: &str,
+    ) -> Result<Vec<Utf8PathBuf>> {
+        Ok(self.cargo_package(pkg_name)?["targets"]
+            .members()
+            .filter(|target| target["kind"].contains(kind))
+            .filter_map(|target| target["name"].as_str())
+            .map(|name| {
+                // The libraries use underscores instead of dashes
+                let lib_name = name.replace('-', "_");
+                Utf8PathBuf::from(match kind {
+                    "cdylib" => format!("lib{lib_name}.so"),
+                    "staticlib" => format!("lib{lib_name}.a"),
+                    _ => name.to_string(),
+                })
+            })
+            .collect())
+    }
+
+    /// The files generated in a subdirectory of the output directory, i.e. the
+    /// man pages generated by clap_mangen in target/release/man
+    pub fn generated_files(
+        &self,
+        dir: &str,
+    ) -> Result<Vec<Utf8PathBuf>> {
+        let outputdir = self
+            .outputdir
+            .as_ref()
+            .context("auto entries can only be used when installing from a cargo project")?;
+        let generated_dir = outputdir.join(dir);
+        if !generated_dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut files = Vec::new();
+        for entry in generated_dir
+            .read_dir_utf8()
+            .with_context(|| format!("unable to read directory {generated_dir}"))?
+        {
+            let entry =
+                entry.with_context(|| format!("unable to read directory {generated_dir}"))?;
+            if entry.file_type()?.is_file() {
+                files.push(Utf8Path::new(dir).join(entry.file_name()));
+            }
+        }
+        files.sort();
+
+        Ok(files)
+    }
+}
+
+/// The root of the cargo workspace containing the package directory, or the
+/// package directory itself when it is not a workspace member
+pub fn workspace_root(package_dir: &Utf8Path) -> Utf8PathBuf {
+    package_dir
+        .ancestors()
+        .find(|dir| {
+            fs::read_to_string(dir.join("Cargo.toml"))
+                .ok()
+                .and_then(|cargo_toml| cargo_toml.parse::<toml::Table>().ok())
+                .is_some_and(|cargo_toml| cargo_toml.contains_key("workspace"))
+        })
+        .unwrap_or(package_dir)
+        .to_path_buf()
+}
+
+// Run the command as the user invoking rinstall, instead of root when using sudo
+fn as_invoking_user(command: &mut Command) -> &mut Command {
+    let uid =
+        env::var("SUDO_UID").map_or(unsafe { libc::getuid() }, |uid| uid.parse::<u32>().unwrap());
+    let gid =
+        env::var("SUDO_GID").map_or(unsafe { libc::getgid() }, |gid| gid.parse::<u32>().unwrap());
+    if env::var("SUDO_UID").is_ok() {
+        // sudo might keep HOME of the root user, i.e. cargo would use its registry
+        let passwd = unsafe { libc::getpwuid(uid) };
+        if !passwd.is_null() {
+            let home = unsafe { CStr::from_ptr((*passwd).pw_dir) };
+            command.env("HOME", OsStr::from_bytes(home.to_bytes()));
+        }
+    }
+
+    command.uid(uid).gid(gid)
+}
+
+/// Run a build command of the project in the package directory, as the user
+/// invoking rinstall
+pub fn run_build_command(
+    package_dir: &Utf8Path,
+    command: &str,
+) -> Result<()> {
+    info!("Running {}", command.bold());
+    let status = as_invoking_user(
+        Command::new("sh")
+            .args(["-c", command])
+            .current_dir(package_dir),
+    )
+    .status()
+    .with_context(|| format!("unable to run `{command}`"))?;
+    ensure!(status.success(), "`{command}` failed with {status}");
+
+    Ok(())
+}
+
+/// Run a command of the project in the package directory, as the user invoking
+/// rinstall, and return its output, i.e. the completions printed by the executable
+pub fn run_generate_command(
+    package_dir: &Utf8Path,
+    command: &str,
+) -> Result<Vec<u8>> {
+    info!("Running {}", command.bold());
+    let output = as_invoking_user(
+        Command::new("sh")
+            .args(["-c", command])
+            .current_dir(package_dir)
+            .stderr(Stdio::inherit()),
+    )
+    .output()
+    .with_context(|| format!("unable to run `{command}`"))?;
+    ensure!(
+        output.status.success(),
+        "`{command}` failed with {}",
+        output.status
+    );
+
+    Ok(output.stdout)
+}
+
+// Run `cargo metadata` for the crates in the package directory
+fn cargo_metadata(package_dir: &Utf8Path) -> Result<JsonValue> {
+    // cargo metadata only works when running as the current user that has built
+    // the project. Otherwise it will use metadata for the root user and
+    // it is almost never what we want
+    let output = as_invoking_user(
+        Command::new("cargo")
+            .args(["metadata", "--no-deps", "--format-version", "1"])
+            .current_dir(package_dir),
+    )
+    .output()
+    .context("unable to run `cargo metadata`")?;
+    ensure!(
+        output.status.success(),
+        "`cargo metadata` failed: {}",
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    json::parse(&String::from_utf8_lossy(&output.stdout))
+        .context("unable to parse JSON from `cargo metadata` output")
 }
 
 impl ProjectDirectories for RustDirectories {
@@ -130,11 +365,77 @@ impl ProjectDirectories for RustDirectories {
     }
 }
 
+// The build directory of a project, the sources are looked up there first
+// and then in the project directory, i.e. for scripts
+pub struct BuildDirectories {
+    pub packagedir: Utf8PathBuf,
+    pub builddir: Option<Utf8PathBuf>,
+}
+
+impl BuildDirectories {
+    pub fn new(
+        pkg_type: Type,
+        package_dir: &Utf8Path,
+        build_dir: Option<Utf8PathBuf>,
+    ) -> Self {
+        let builddir = build_dir.or_else(|| match pkg_type {
+            Type::Meson => Some(Self::detect(
+                package_dir,
+                &["builddir", "build", "_build"],
+                "meson-private",
+            )),
+            Type::CMake => Some(Self::detect(
+                package_dir,
+                &["build", "_build"],
+                "CMakeCache.txt",
+            )),
+            // `go build` writes the binaries in the project directory, unless -o is used
+            _ => None,
+        });
+
+        Self {
+            packagedir: package_dir.to_path_buf(),
+            builddir,
+        }
+    }
+
+    // Use the first candidate that has been configured by the build system,
+    // i.e. the one containing the given file
+    fn detect(
+        package_dir: &Utf8Path,
+        candidates: &[&str],
+        marker: &str,
+    ) -> Utf8PathBuf {
+        candidates
+            .iter()
+            .map(|candidate| package_dir.join(candidate))
+            .find(|candidate| candidate.join(marker).exists())
+            .unwrap_or_else(|| package_dir.join(candidates[0]))
+    }
+}
+
+impl ProjectDirectories for BuildDirectories {
+    fn sourcepath(
+        &'static self,
+        source: &Utf8Path,
+    ) -> Utf8PathBuf {
+        self.builddir
+            .as_ref()
+            .map(|builddir| builddir.join(source))
+            .filter(|builddir_source| builddir_source.exists())
+            .unwrap_or_else(|| self.packagedir.join(source))
+    }
+}
+
 impl ProjectDirectories for DefaultProjectDirectories {
     fn sourcepath(
         &'static self,
         source: &Utf8Path,
     ) -> Utf8PathBuf {
-        source.to_path_buf()
+        // Packagedir is not set when installing from a tarball
+        self.packagedir.as_ref().map_or_else(
+            || source.to_path_buf(),
+            |packagedir| packagedir.join(source),
+        )
     }
 }
@@ -0,0 +1,384 @@
+use std::fs;
+
+use camino::{Utf8Path, Utf8PathBuf};
+use color_eyre::{eyre::Context, Result};
+use json::{array, object};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::utils::timestamp;
+
+// Phrases found in the well-known license texts, along with their SPDX identifier.
+// The more specific licenses come first
+const LICENSE_TEXTS: [(&[&str], &str); 14] = [
+    (
+        &["gnu affero general public license", "version 3"],
+        "AGPL-3.0-only",
+    ),
+    (
+        &["gnu lesser general public license", "version 3"],
+        "LGPL-3.0-only",
+    ),
+    (
+        &["gnu lesser general public license", "version 2.1"],
+        "LGPL-2.1-only",
+    ),
+    (&["gnu general public license", "version 3"], "GPL-3.0-only"),
+    (&["gnu general public license", "version 2"], "GPL-2.0-only"),
+    (&["apache license", "version 2.0"], "Apache-2.0"),
+    (&["mozilla public license version 2.0"], "MPL-2.0"),
+    (&["permission is hereby granted, free of charge"], "MIT"),
+    (
+        &[
+            "permission to use, copy, modify, and/or distribute this software for any purpose",
+            "with or without fee",
+        ],
+        "ISC",
+    ),
+    (
+        &[
+            "redistribution and use in source and binary forms",
+            "neither the name",
+        ],
+        "BSD-3-Clause",
+    ),
+    (
+        &["redistribution and use in source and binary forms"],
+        "BSD-2-Clause",
+    ),
+    (
+        &["this is free and unencumbered software released into the public domain"],
+        "Unlicense",
+    ),
+    (
+        &["this software is provided 'as-is', without any express or implied"],
+        "Zlib",
+    ),
+    (
+        &["creative commons legal code", "cc0 1.0 universal"],
+        "CC0-1.0",
+    ),
+];
+
+/// Returns the SPDX identifier of a license file, either from its
+/// SPDX-License-Identifier tag or by looking for a well-known license text
+pub fn detect_license(contents: &str) -> Option<String> {
+    if let Some(license) = contents.lines().find_map(|line| {
+        line.split_once("SPDX-License-Identifier:")
+            .map(|(_, license)| license.trim())
+            .filter(|license| !license.is_empty())
+    }) {
+        return Some(license.to_string());
+    }
+
+    // Ignore the line wrapping and the case of the text
+    let contents = contents
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    LICENSE_TEXTS
+        .iter()
+        .find(|(phrases, _)| phrases.iter().all(|phrase| contents.contains(phrase)))
+        .map(|(_, license)| license.to_string())
+}
+
+/// A file listed in the SBOM
+pub struct SbomFile {
+    // The installed path, without the destdir
+    pub path: Utf8PathBuf,
+    pub sha1: String,
+    pub sha256: String,
+    pub blake3: String,
+    // The license detected in the file, only for the licenses entries
+    pub license: Option<String>,
+}
+
+impl SbomFile {
+    /// Read the file from real_path, which contains the destdir when packaging
+    pub fn new(
+        path: &Utf8Path,
+        real_path: &Utf8Path,
+        is_license: bool,
+    ) -> Result<Self> {
+        let contents =
+            fs::read(real_path).with_context(|| format!("unable to read file {real_path}"))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            sha1: format!("{:x}", Sha1::digest(&contents)),
+            sha256: format!("{:x}", Sha256::digest(&contents)),
+            blake3: blake3::hash(&contents).to_hex().to_string(),
+            license: if is_license {
+                detect_license(&String::from_utf8_lossy(&contents))
+            } else {
+                None
+            },
+        })
+    }
+}
+
+/// A package listed in the SBOM
+pub struct SbomPackage {
+    pub name: String,
+    pub version: Option<String>,
+    pub homepage: Option<String>,
+    // The license written in install.yml
+    pub license: Option<String>,
+    pub files: Vec<SbomFile>,
+}
+
+impl SbomPackage {
+    /// The license written in install.yml, otherwise the ones detected
+    /// in the licenses entries, sorted and deduplicated
+    fn licenses(&self) -> Vec<&str> {
+        if let Some(license) = &self.license {
+            return vec![license];
+        }
+        let mut licenses = self
+            .files
+            .iter()
+            .filter_map(|file| file.license.as_deref())
+            .collect::<Vec<_>>();
+        licenses.sort_unstable();
+        licenses.dedup();
+        licenses
+    }
+
+    fn declared_license(&self) -> String {
+        let licenses = self.licenses();
+        match licenses.as_slice() {
+            [] => "NOASSERTION".to_string(),
+            [license] => license.to_string(),
+            // Wrap the compound expressions to keep their meaning
+            licenses => licenses
+                .iter()
+                .map(|license| {
+                    if license.contains(' ') {
+                        format!("({license})")
+                    } else {
+                        license.to_string()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" AND "),
+        }
+    }
+}
+
+// An identifier derived from the content of the SBOM, formatted as a UUID
+fn content_uuid(
+    packages: &[SbomPackage],
+    timestamp: &str,
+) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(timestamp.as_bytes());
+    for package in packages {
+        hasher.update(package.name.as_bytes());
+        hasher.update(package.version.as_deref().unwrap_or_default().as_bytes());
+        for file in &package.files {
+            hasher.update(file.path.as_str().as_bytes());
+            hasher.update(file.blake3.as_bytes());
+        }
+    }
+    let mut bytes = *hasher.finalize().as_bytes();
+    // Version 8 (custom) and RFC 4122 variant
+    bytes[6] = (bytes[6] & 0x0f) | 0x80;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = bytes[..16]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+// SPDX identifiers can only contain letters, numbers, . and -
+fn spdx_id(
+    kind: &str,
+    name: &str,
+) -> String {
+    let name = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+    format!("SPDXRef-{kind}-{name}")
+}
+
+/// Render the SBOM as a SPDX 2.3 JSON document
+pub fn spdx(packages: &[SbomPackage]) -> String {
+    let timestamp = timestamp();
+    let name = packages
+        .iter()
+        .map(|package| package.name.as_str())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    let mut spdx_packages = array![];
+    let mut spdx_files = array![];
+    let mut relationships = array![];
+    for package in packages {
+        let package_id = spdx_id("Package", &package.name);
+        let mut file_ids = array![];
+        let mut sha1s = Vec::new();
+        for (i, file) in package.files.iter().enumerate() {
+            let file_id = spdx_id("File", &format!("{}-{i}", package.name));
+            let mut spdx_file = object! {
+                "fileName": format!(".{}", Utf8Path::new("/").join(&file.path)),
+                "SPDXID": file_id.as_str(),
+                "checksums": [
+                    { "algorithm": "SHA1", "checksumValue": file.sha1.as_str() },
+                    { "algorithm": "SHA256", "checksumValue": file.sha256.as_str() },
+                    { "algorithm": "BLAKE3", "checksumValue": file.blake3.as_str() },
+                ],
+                "licenseConcluded": "NOASSERTION",
+                "copyrightText": "NOASSERTION",
+            };
+            if let Some(license) = &file.license {
+                spdx_file["licenseInfoInFiles"] = array![license.as_str()];
+            }
+            spdx_files.push(spdx_file).unwrap();
+            file_ids.push(file_id.as_str()).unwrap();
+            relationships
+                .push(object! {
+                    "spdxElementId": package_id.as_str(),
+                    "relationshipType": "CONTAINS",
+                    "relatedSpdxElement": file_id,
+                })
+                .unwrap();
+            sha1s.push(file.sha1.as_str());
+        }
+
+        // The verification code is the SHA1 of the sorted SHA1 of the files
+        sha1s.sort_unstable();
+        let mut spdx_package = object! {
+            "name": package.name.as_str(),
+            "SPDXID": package_id.as_str(),
+            "downloadLocation": "NOASSERTION",
+            "filesAnalyzed": true,
+            "packageVerificationCode": {
+                "packageVerificationCodeValue": format!("{:x}", Sha1::digest(sha1s.concat())),
+            },
+            "licenseConcluded": "NOASSERTION",
+            "licenseDeclared": package.declared_license(),
+            "copyrightText": "NOASSERTION",
+            "hasFiles": file_ids,
+        };
+        if let Some(version) = &package.version {
+            spdx_package["versionInfo"] = version.as_str().into();
+        }
+        if let Some(homepage) = &package.homepage {
+            spdx_package["homepage"] = homepage.as_str().into();
+        }
+        spdx_packages.push(spdx_package).unwrap();
+        relationships
+            .push(object! {
+                "spdxElementId": "SPDXRef-DOCUMENT",
+                "relationshipType": "DESCRIBES",
+                "relatedSpdxElement": package_id,
+            })
+            .unwrap();
+    }
+
+    object! {
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": name.as_str(),
+        "documentNamespace": format!(
+            "https://spdx.org/spdxdocs/{name}-{}",
+            content_uuid(packages, &timestamp)
+        ),
+        "creationInfo": {
+            "created": timestamp.as_str(),
+            "creators": [format!("Tool: rinstall-{}", env!("CARGO_PKG_VERSION"))],
+        },
+        "packages": spdx_packages,
+        "files": spdx_files,
+        "relationships": relationships,
+    }
+    .pretty(2)
+        + "\n"
+}
+
+/// Render the SBOM as a CycloneDX 1.5 JSON document
+pub fn cyclonedx(packages: &[SbomPackage]) -> String {
+    let timestamp = timestamp();
+
+    let mut components = array![];
+    for package in packages {
+        let mut files = array![];
+        for file in &package.files {
+            let path = Utf8Path::new("/").join(&file.path);
+            files
+                .push(object! {
+                    "type": "file",
+                    "bom-ref": format!("{}:{path}", package.name),
+                    "name": path.as_str(),
+                    "hashes": [
+                        { "alg": "SHA-1", "content": file.sha1.as_str() },
+                        { "alg": "SHA-256", "content": file.sha256.as_str() },
+                        { "alg": "BLAKE3", "content": file.blake3.as_str() },
+                    ],
+                })
+                .unwrap();
+        }
+
+        let mut component = object! {
+            "type": "application",
+            "bom-ref": package.name.as_str(),
+            "name": package.name.as_str(),
+            "components": files,
+        };
+        if let Some(version) = &package.version {
+            component["version"] = version.as_str().into();
+        }
+        let licenses = package.licenses();
+        if licenses.iter().any(|license| license.contains(' ')) {
+            component["licenses"] = array![{ "expression": package.declared_license() }];
+        } else if !licenses.is_empty() {
+            component["licenses"] = licenses
+                .iter()
+                .map(|license| object! { "license": { "id": *license } })
+                .collect::<Vec<_>>()
+                .into();
+        }
+        if let Some(homepage) = &package.homepage {
+            component["externalReferences"] =
+                array![{ "type": "website", "url": homepage.as_str() }];
+        }
+        components.push(component).unwrap();
+    }
+
+    object! {
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "serialNumber": format!("urn:uuid:{}", content_uuid(packages, &timestamp)),
+        "version": 1,
+        "metadata": {
+            "timestamp": timestamp.as_str(),
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": "rinstall",
+                    "version": env!("CARGO_PKG_VERSION"),
+                }],
+            },
+        },
+        "components": components,
+    }
+    .pretty(2)
+        + "\n"
+}
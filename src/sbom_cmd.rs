@@ -0,0 +1,47 @@
+#[derive(ValueEnum, Clone, Copy)]
+pub enum SbomFormat {
+    #[clap(help = "SPDX 2.3 JSON document")]
+    Spdx,
+    #[clap(help = "CycloneDX 1.5 JSON document")]
+    Cyclonedx,
+}
+
+#[derive(Args, Clone)]
+pub struct SbomCmd {
+    #[clap(help = "Path to the rinstall.yml configuration", from_global)]
+    pub config: Option<String>,
+    #[clap(long = "system", help = "Use the packages installed system-wide")]
+    pub system: bool,
+    #[clap(
+        long,
+        env,
+        requires = "system",
+        help = concat!("A prefix used in constructing the default values of the directories",
+                       " listed below. (system only)",
+                       " [default: /usr/local]")
+    )]
+    pub prefix: Option<String>,
+    #[clap(
+        long,
+        env,
+        help = concat!("The directory containing the rinstall pkginfo files",
+                       " [system default: @prefix@/var] [user default: @XDG_DATA_HOME@]")
+    )]
+    pub localstatedir: Option<String>,
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = SbomFormat::Spdx,
+        help = "Format of the SBOM"
+    )]
+    pub format: SbomFormat,
+    #[clap(
+        short = 'o',
+        long,
+        help = "Path where the SBOM is written [default: the standard output]"
+    )]
+    pub output: Option<std::path::PathBuf>,
+    #[clap(help = concat!("The names or pkginfo files of the packages to list",
+                          " [default: all the installed packages]"))]
+    pub packages: Vec<String>,
+}
@@ -0,0 +1,125 @@
+use std::fs;
+
+use camino::Utf8Path;
+use clap::{Args, ValueEnum};
+use color_eyre::{
+    eyre::{Context, ContextCompat},
+    Result,
+};
+use colored::Colorize;
+use log::{info, warn};
+
+use crate::{
+    dirs::Dirs,
+    dirs_config_impl::DirsConfig,
+    package_info::PackageInfo,
+    sbom::{self, SbomFile, SbomPackage},
+};
+
+include!("sbom_cmd.rs");
+
+impl SbomCmd {
+    // Returns true if we need to use the system directories
+    pub fn system_dirs(&self) -> bool {
+        (unsafe { libc::getuid() } == 0) || self.system
+    }
+
+    pub fn run(self) -> Result<()> {
+        let mut opt_dirs = DirsConfig {
+            prefix: self.prefix.clone(),
+            localstatedir: self.localstatedir.clone(),
+            ..Default::default()
+        };
+        let dirs_config =
+            DirsConfig::load(self.config.as_deref(), self.system_dirs(), &mut opt_dirs)?;
+        let dirs = Dirs::new(dirs_config, self.system_dirs()).context("unable to create dirs")?;
+
+        let pkgs = if self.packages.is_empty() {
+            installed_packages(&dirs)?
+        } else {
+            self.packages.clone()
+        };
+
+        let mut packages = Vec::new();
+        for pkg in &pkgs {
+            let pkg_info = PackageInfo::load(pkg, &dirs)?;
+            // The licenses are installed in the directories used when installing the package
+            let licensesdir = pkg_info
+                .dirs
+                .as_ref()
+                .unwrap_or_else(|| {
+                    warn!(
+                        "pkginfo for {} does not contain the installation directories, using the current ones",
+                        pkg_info.pkg_name.blue().italic()
+                    );
+                    &dirs
+                })
+                .datarootdir
+                .join("licenses");
+
+            let mut files = Vec::new();
+            for file in &pkg_info.files {
+                if !file.path.exists() {
+                    warn!(
+                        "file {} does not exist anymore, skipping it",
+                        file.path.as_str().yellow().bold()
+                    );
+                    continue;
+                }
+                let sbom_file =
+                    SbomFile::new(&file.path, &file.path, file.path.starts_with(&licensesdir))?;
+                if sbom_file.blake3 != file.checksum {
+                    warn!(
+                        "file {} has been modified after its installation",
+                        file.path.as_str().yellow().bold()
+                    );
+                }
+                files.push(sbom_file);
+            }
+
+            packages.push(SbomPackage {
+                name: pkg_info.pkg_name,
+                version: pkg_info.version,
+                homepage: pkg_info.homepage,
+                license: pkg_info.license,
+                files,
+            });
+        }
+
+        let contents = match self.format {
+            SbomFormat::Spdx => sbom::spdx(&packages),
+            SbomFormat::Cyclonedx => sbom::cyclonedx(&packages),
+        };
+        if let Some(output) = &self.output {
+            let output = Utf8Path::from_path(output)
+                .with_context(|| format!("{output:?} contains invalid UTF-8 characters"))?;
+            info!("Writing {}", output.as_str().cyan().bold());
+            fs::write(output, contents).with_context(|| format!("unable to write {output}"))?;
+        } else {
+            print!("{contents}");
+        }
+
+        Ok(())
+    }
+}
+
+// The pkginfo files of all the packages installed in localstatedir
+fn installed_packages(dirs: &Dirs) -> Result<Vec<String>> {
+    let pkg_info_dir = dirs.localstatedir.join("rinstall");
+    if !pkg_info_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut packages = Vec::new();
+    for entry in pkg_info_dir
+        .read_dir_utf8()
+        .with_context(|| format!("unable to read directory {pkg_info_dir}"))?
+    {
+        let entry = entry.with_context(|| format!("unable to read directory {pkg_info_dir}"))?;
+        if entry.file_type()?.is_file() && entry.path().extension() == Some("pkg") {
+            packages.push(entry.path().to_string());
+        }
+    }
+    packages.sort();
+
+    Ok(packages)
+}
@@ -0,0 +1,193 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{Read, Write},
+    os::unix::fs::{OpenOptionsExt, PermissionsExt},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use camino::{Utf8Path, Utf8PathBuf};
+use color_eyre::{
+    eyre::{bail, ensure, Context, ContextCompat},
+    Result,
+};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+
+// Keys and signatures are stored in a minisign-like format: an untrusted comment
+// followed by a base64 line containing the algorithm, the key id and the payload
+const ALGORITHM: &[u8; 2] = b"Ed";
+const KEY_ID_LEN: usize = 8;
+
+pub struct SecretKey {
+    key_id: [u8; KEY_ID_LEN],
+    key: SigningKey,
+}
+
+pub struct PublicKey {
+    key_id: [u8; KEY_ID_LEN],
+    key: VerifyingKey,
+}
+
+fn key_id(key: &VerifyingKey) -> [u8; KEY_ID_LEN] {
+    let mut key_id = [0; KEY_ID_LEN];
+    key_id.copy_from_slice(&blake3::hash(key.as_bytes()).as_bytes()[..KEY_ID_LEN]);
+    key_id
+}
+
+fn encode(
+    comment: &str,
+    key_id: &[u8; KEY_ID_LEN],
+    payload: &[u8],
+) -> String {
+    let mut buf = Vec::with_capacity(ALGORITHM.len() + KEY_ID_LEN + payload.len());
+    buf.extend_from_slice(ALGORITHM);
+    buf.extend_from_slice(key_id);
+    buf.extend_from_slice(payload);
+    format!("untrusted comment: {comment}\n{}\n", STANDARD.encode(buf))
+}
+
+// Returns the key id and the payload contained in the file
+fn decode<const N: usize>(file: &Utf8Path) -> Result<([u8; KEY_ID_LEN], [u8; N])> {
+    let contents =
+        fs::read_to_string(file).with_context(|| format!("unable to read file {file:?}"))?;
+    let line = contents
+        .lines()
+        .find(|line| !line.is_empty() && !line.starts_with("untrusted comment:"))
+        .with_context(|| format!("{file:?} does not contain any key or signature"))?;
+    let buf = STANDARD
+        .decode(line.trim())
+        .with_context(|| format!("{file:?} contains invalid base64 data"))?;
+    ensure!(
+        buf.len() == ALGORITHM.len() + KEY_ID_LEN + N,
+        "{file:?} has an invalid length"
+    );
+    ensure!(
+        &buf[..ALGORITHM.len()] == ALGORITHM,
+        "{file:?} uses an unsupported signature algorithm"
+    );
+    let mut key_id = [0; KEY_ID_LEN];
+    key_id.copy_from_slice(&buf[ALGORITHM.len()..ALGORITHM.len() + KEY_ID_LEN]);
+    let mut payload = [0; N];
+    payload.copy_from_slice(&buf[ALGORITHM.len() + KEY_ID_LEN..]);
+
+    Ok((key_id, payload))
+}
+
+impl SecretKey {
+    pub fn generate() -> Result<Self> {
+        let mut seed = [0; ed25519_dalek::SECRET_KEY_LENGTH];
+        File::open("/dev/urandom")
+            .and_then(|mut urandom| urandom.read_exact(&mut seed))
+            .context("unable to read random bytes from /dev/urandom")?;
+        let key = SigningKey::from_bytes(&seed);
+        Ok(Self {
+            key_id: key_id(&key.verifying_key()),
+            key,
+        })
+    }
+
+    pub fn load(file: &Utf8Path) -> Result<Self> {
+        let (key_id, seed) = decode(file)?;
+        let key = SigningKey::from_bytes(&seed);
+        ensure!(
+            self::key_id(&key.verifying_key()) == key_id,
+            "the key id in {file:?} does not match its secret key"
+        );
+        Ok(Self { key_id, key })
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey {
+            key_id: self.key_id,
+            key: self.key.verifying_key(),
+        }
+    }
+
+    pub fn save(
+        &self,
+        file: &Utf8Path,
+    ) -> Result<()> {
+        // The secret key must only be readable by its owner
+        let mut secret_key = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(file)
+            .with_context(|| format!("unable to open file {file:?}"))?;
+        // The mode is only applied when creating the file, i.e. not with --force
+        fs::set_permissions(file, fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("unable to set the permissions of {file:?}"))?;
+        secret_key
+            .write_all(encode("rinstall secret key", &self.key_id, self.key.as_bytes()).as_bytes())
+            .with_context(|| format!("unable to write secret key to {file:?}"))
+    }
+
+    /// Create a detached signature for the file, stored in <file>.sig
+    pub fn sign_file(
+        &self,
+        file: &Utf8Path,
+    ) -> Result<Utf8PathBuf> {
+        let contents = fs::read(file).with_context(|| format!("unable to read file {file:?}"))?;
+        let signature = self.key.sign(&contents);
+        let signature_file = signature_path(file);
+        fs::write(
+            &signature_file,
+            encode(
+                &format!("signature of {}", file.file_name().unwrap_or(file.as_str())),
+                &self.key_id,
+                &signature.to_bytes(),
+            ),
+        )
+        .with_context(|| format!("unable to write signature to {signature_file:?}"))?;
+
+        Ok(signature_file)
+    }
+}
+
+impl PublicKey {
+    pub fn load(file: &Utf8Path) -> Result<Self> {
+        let (key_id, key) = decode(file)?;
+        let key = VerifyingKey::from_bytes(&key)
+            .with_context(|| format!("{file:?} does not contain a valid public key"))?;
+        ensure!(
+            self::key_id(&key) == key_id,
+            "the key id in {file:?} does not match its public key"
+        );
+        Ok(Self { key_id, key })
+    }
+
+    pub fn save(
+        &self,
+        file: &Utf8Path,
+    ) -> Result<()> {
+        fs::write(
+            file,
+            encode("rinstall public key", &self.key_id, self.key.as_bytes()),
+        )
+        .with_context(|| format!("unable to write public key to {file:?}"))
+    }
+
+    /// Verify the detached signature of a file
+    pub fn verify_file(
+        &self,
+        file: &Utf8Path,
+        signature_file: &Utf8Path,
+    ) -> Result<()> {
+        ensure!(
+            signature_file.exists(),
+            "{file} is not signed, signature {signature_file} does not exist"
+        );
+        let (key_id, signature) = decode(signature_file)?;
+        if key_id != self.key_id {
+            bail!("{file} has been signed with a different key");
+        }
+        let contents = fs::read(file).with_context(|| format!("unable to read file {file:?}"))?;
+        self.key
+            .verify_strict(&contents, &Signature::from_bytes(&signature))
+            .with_context(|| format!("invalid signature for {file}"))
+    }
+}
+
+pub fn signature_path(file: &Utf8Path) -> Utf8PathBuf {
+    Utf8PathBuf::from(format!("{file}.sig"))
+}
@@ -0,0 +1,165 @@
+use std::{
+    fs::{self, File},
+    path::PathBuf,
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use color_eyre::{
+    eyre::{bail, Context, ContextCompat},
+    Result,
+};
+use tempfile::TempDir;
+use walkdir::WalkDir;
+
+use crate::{dirs_config_impl::DirsConfig, manifest::Manifest, InstallCmd};
+
+/// The options selecting the build output of the project to package
+pub struct BuildOptions {
+    pub rust_debug_target: bool,
+    pub rust_profile: Option<String>,
+    pub rust_target_triple: Option<String>,
+    pub build_dir: Option<String>,
+}
+
+/// A package installed into a temporary directory, using the directories
+/// of a distribution, from which the distribution packages are built
+pub struct StagedPackage {
+    // The directory is removed when the package is dropped
+    root: TempDir,
+    pub manifest: Manifest,
+}
+
+impl StagedPackage {
+    /// The system directories shared by the distributions, adjust them
+    /// before creating the package when needed
+    pub fn system_dirs() -> DirsConfig {
+        DirsConfig {
+            prefix: Some("/usr".to_string()),
+            sysconfdir: Some("/etc".to_string()),
+            localstatedir: Some("/var".to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub fn new(
+        dirs: DirsConfig,
+        package_dir: PathBuf,
+        pkg_name: &str,
+        build_options: BuildOptions,
+    ) -> Result<Self> {
+        let root = tempfile::Builder::new()
+            .prefix("rinstall-")
+            .tempdir()
+            .context("unable to create staging directory")?;
+        let destdir = Utf8Path::from_path(root.path())
+            .with_context(|| format!("{:?} contains invalid UTF-8 characters", root.path()))?;
+
+        // The configuration of the invoking user must not end up in the package
+        let mut install = InstallCmd {
+            config: None,
+            skip_config: true,
+            system: false,
+            accept_changes: true,
+            force: false,
+            update_config: false,
+            rust_debug_target: build_options.rust_debug_target,
+            rust_profile: build_options.rust_profile,
+            rust_target_triple: build_options.rust_target_triple,
+            destdir: Some(destdir.to_string()),
+            skip_pkg_info: false,
+            package_dir,
+            build_dir: build_options.build_dir,
+            build: false,
+            strip: false,
+            split_debug: None,
+            deny_elf_warnings: false,
+            packages: vec![pkg_name.to_string()],
+            update: false,
+            packaging: true,
+            files_manifest: Vec::new(),
+            completions: None,
+            tarball: None,
+            verify_key: None,
+            signature: None,
+            dirs,
+        };
+        let manifest = install
+            .install()?
+            .pop()
+            .with_context(|| format!("package {pkg_name} not found in install.yml"))?;
+
+        Ok(Self { root, manifest })
+    }
+
+    pub fn root(&self) -> &Utf8Path {
+        // The path has already been checked when creating the package
+        Utf8Path::from_path(self.root.path()).unwrap()
+    }
+
+    /// Returns the files and directories of the package, relative to its root
+    pub fn entries(&self) -> Result<Vec<Utf8PathBuf>> {
+        WalkDir::new(self.root())
+            .min_depth(1)
+            .sort_by_file_name()
+            .into_iter()
+            .map(|entry| -> Result<Utf8PathBuf> {
+                let entry = entry.context("unable to read staging directory")?;
+                let path = Utf8Path::from_path(entry.path()).with_context(|| {
+                    format!("{:?} contains invalid UTF-8 characters", entry.path())
+                })?;
+                Ok(path.strip_prefix(self.root()).unwrap().to_path_buf())
+            })
+            .collect()
+    }
+
+    /// The size of the files installed, in bytes
+    pub fn installed_size(&self) -> Result<u64> {
+        let mut size = 0;
+        for entry in self.entries()? {
+            let path = self.root().join(&entry);
+            let metadata = fs::symlink_metadata(&path)
+                .with_context(|| format!("unable to read metadata of {path}"))?;
+            if metadata.is_file() {
+                size += metadata.len();
+            }
+        }
+
+        Ok(size)
+    }
+
+    /// Append all the files and directories of the package to the archive, owned by root
+    pub fn append_to_archive(
+        &self,
+        archive: &mut tar::Builder<Vec<u8>>,
+    ) -> Result<()> {
+        for entry in self.entries()? {
+            let path = self.root().join(&entry);
+            let metadata = fs::symlink_metadata(&path)
+                .with_context(|| format!("unable to read metadata of {path}"))?;
+            let mut header = tar::Header::new_gnu();
+            header.set_metadata(&metadata);
+            header.set_uid(0);
+            header.set_gid(0);
+            header.set_username("root")?;
+            header.set_groupname("root")?;
+            if metadata.is_dir() {
+                archive.append_data(&mut header, &entry, std::io::empty())
+            } else if metadata.is_file() {
+                archive.append_data(
+                    &mut header,
+                    &entry,
+                    File::open(&path).with_context(|| format!("unable to open file {path}"))?,
+                )
+            } else if metadata.is_symlink() {
+                let target = fs::read_link(&path)
+                    .with_context(|| format!("unable to read symlink {path}"))?;
+                archive.append_link(&mut header, &entry, target)
+            } else {
+                bail!("{path} is neither a file, a directory nor a symlink");
+            }
+            .with_context(|| format!("unable to append {entry} to the archive"))?;
+        }
+
+        Ok(())
+    }
+}
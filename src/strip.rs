@@ -0,0 +1,113 @@
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use object::{
+    build::elf::{Builder, SectionData},
+    elf,
+    read::elf::{ElfFile32, ElfFile64},
+    Endianness, FileKind, Object,
+};
+
+/// An ELF file without its symbols and debug information
+pub struct StrippedElf {
+    pub data: Vec<u8>,
+    // The build-id of the file, used to name its debug file
+    pub build_id: Option<String>,
+    // The symbols and debug information, only available when splitting them
+    pub debug: Option<Vec<u8>>,
+}
+
+// The sections removed from the stripped file, i.e. the static symbol table
+// and the DWARF sections, optionally compressed
+fn is_debug_section(
+    name: &[u8],
+    data: &SectionData,
+) -> bool {
+    matches!(
+        data,
+        SectionData::Symbol | SectionData::SymbolSectionIndex | SectionData::String
+    ) || name.starts_with(b".debug")
+        || name.starts_with(b".zdebug")
+}
+
+/// Strip an executable or a shared library, returns None for the files that
+/// are not ELF or that cannot be stripped, i.e. object files
+pub fn strip(
+    data: &[u8],
+    split_debug: bool,
+) -> Result<Option<StrippedElf>> {
+    let build_id = match FileKind::parse(data) {
+        Ok(FileKind::Elf32) => ElfFile32::<Endianness>::parse(data)?.build_id()?,
+        Ok(FileKind::Elf64) => ElfFile64::<Endianness>::parse(data)?.build_id()?,
+        _ => return Ok(None),
+    }
+    .map(|build_id| {
+        build_id
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>()
+    });
+
+    let read = |data| Builder::read(data).map_err(|err| eyre!("{err}"));
+    let mut builder = read(data).context("unable to parse ELF file")?;
+    // The symbols of object files are needed for linking
+    if !matches!(builder.header.e_type, elf::ET_EXEC | elf::ET_DYN) {
+        return Ok(None);
+    }
+    for section in &mut builder.sections {
+        section.delete = is_debug_section(&section.name, &section.data);
+    }
+    for symbol in &mut builder.symbols {
+        symbol.delete = true;
+    }
+    let mut stripped = Vec::new();
+    builder
+        .write(&mut stripped)
+        .map_err(|err| eyre!("{err}"))
+        .context("unable to write stripped ELF file")?;
+
+    let debug = if split_debug {
+        Some(debug_file(read(data)?)?)
+    } else {
+        None
+    };
+
+    Ok(Some(StrippedElf {
+        data: stripped,
+        build_id,
+        debug,
+    }))
+}
+
+// Keep the debug sections and the headers of the others, same as
+// `objcopy --only-keep-debug`, the debuggers find it by its build-id
+fn debug_file(mut builder: Builder) -> Result<Vec<u8>> {
+    let sizes = builder
+        .sections
+        .iter()
+        .map(|section| builder.section_size(section) as u64)
+        .collect::<Vec<_>>();
+    for (section, size) in builder.sections.iter_mut().zip(sizes) {
+        if section.is_alloc() && section.sh_type != elf::SHT_NOTE {
+            section.sh_type = elf::SHT_NOBITS;
+            section.data = SectionData::UninitializedData(size);
+        }
+    }
+    // Their tables are not written anymore
+    for symbol in &mut builder.dynamic_symbols {
+        symbol.delete = true;
+    }
+    builder.version_base = None;
+    for version in &mut builder.versions {
+        version.delete = true;
+    }
+
+    let mut debug = Vec::new();
+    builder
+        .write(&mut debug)
+        .map_err(|err| eyre!("{err}"))
+        .context("unable to write ELF debug file")?;
+
+    Ok(debug)
+}
@@ -0,0 +1,274 @@
+use std::{
+    fs::{self, File},
+    io::{BufReader, Read, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use color_eyre::{
+    eyre::{bail, ensure, Context, ContextCompat},
+    Result,
+};
+use flate2::{bufread::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+
+use crate::{elf_check::expected_machine, install_spec::InstallSpec};
+
+// Name of the file containing the tarball metadata, placed next to install.yml
+pub const METADATA_FILE: &str = ".tarball.yml";
+
+// Unknown fields are allowed, so that tarballs created by newer versions of rinstall
+// can still be read
+#[derive(Serialize, Deserialize, Default)]
+pub struct TarballMetadata {
+    // The version of rinstall that created the tarball
+    #[serde(default, rename = "rinstall-version")]
+    pub rinstall_version: Option<String>,
+    // When the tarball contains the payloads for multiple target triples,
+    // each of them is stored in a directory named after the triple
+    #[serde(
+        default,
+        rename = "rust-target-triples",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub rust_target_triples: Vec<String>,
+}
+
+impl TarballMetadata {
+    pub fn new(rust_target_triples: Vec<String>) -> Self {
+        Self {
+            rinstall_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            rust_target_triples,
+        }
+    }
+
+    /// Parse the spec file contained in the tarball
+    pub fn parse_spec(
+        &self,
+        spec_file: String,
+    ) -> Result<InstallSpec> {
+        InstallSpec::new_from_string(spec_file).with_context(|| {
+            if let Some(rinstall_version) = &self.rinstall_version {
+                format!("unable to parse the spec file of the tarball, created by rinstall {rinstall_version}")
+            } else {
+                "unable to parse the spec file of the tarball".to_string()
+            }
+        })
+    }
+
+    /// Return the target triple whose payload shall be installed, if any
+    pub fn select_rust_target_triple(
+        &self,
+        requested: Option<&str>,
+    ) -> Result<Option<&str>> {
+        if self.rust_target_triples.is_empty() {
+            return Ok(None);
+        }
+
+        if let Some(requested) = requested {
+            return self
+                .rust_target_triples
+                .iter()
+                .find(|triple| *triple == requested)
+                .map(|triple| Some(triple.as_str()))
+                .with_context(|| {
+                    format!(
+                        "the tarball does not contain the target triple {requested}, available: {}",
+                        self.rust_target_triples.join(", ")
+                    )
+                });
+        }
+
+        let host = format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS);
+        let candidates = self
+            .rust_target_triples
+            .iter()
+            .filter(|triple| runs_on_host(triple))
+            .collect::<Vec<_>>();
+        // Prefer the triple using the same C library as rinstall, i.e. gnueabihf for gnu
+        let env = if cfg!(target_env = "musl") {
+            "musl"
+        } else {
+            "gnu"
+        };
+        let same_env = candidates
+            .iter()
+            .filter(|triple| {
+                triple
+                    .rsplit('-')
+                    .next()
+                    .is_some_and(|triple_env| triple_env.starts_with(env))
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        let candidates = if same_env.is_empty() {
+            candidates
+        } else {
+            same_env
+        };
+
+        match candidates.as_slice() {
+            [triple] => Ok(Some(triple.as_str())),
+            [] => bail!(
+                "the tarball does not contain a payload for the host {host}, available: {}",
+                self.rust_target_triples.join(", ")
+            ),
+            _ => bail!(
+                "the tarball contains multiple payloads for the host {host}, use --rust-target-triple to select one of: {}",
+                self.rust_target_triples.join(", ")
+            ),
+        }
+    }
+}
+
+/// Returns true if the binaries built for the target triple can run on this host
+pub fn runs_on_host(rust_target_triple: &str) -> bool {
+    // The first part of the triple is not ARCH, i.e. i686 or armv7 for x86 and arm
+    expected_machine(Some(rust_target_triple))
+        .is_some_and(|machine| Some(machine) == expected_machine(None))
+        && rust_target_triple.contains(std::env::consts::OS)
+}
+
+pub struct Tarball {
+    buf: Vec<u8>,
+    pub spec_file: String,
+    pub metadata: TarballMetadata,
+}
+
+impl Tarball {
+    pub fn open(tarball: &Utf8Path) -> Result<Self> {
+        // Decompress the content of the archive
+        let mut decoder = GzDecoder::new(BufReader::new(
+            File::open(tarball).with_context(|| format!("unable to open tarball {tarball}"))?,
+        ));
+        let mut buf = Vec::new();
+        decoder
+            .read_to_end(&mut buf)
+            .with_context(|| format!("unable to decompress tarball {tarball}"))?;
+
+        let mut archive = tar::Archive::new(&buf[..]);
+        let mut tarball_entries = archive
+            .entries()
+            .context("unable to create iterator over tarball archive")?;
+        let mut entry = tarball_entries
+            .next()
+            .context("empty tarball archive")?
+            .context("invalid tarball archive")?;
+        ensure!(
+            entry
+                .path()
+                .context("invalid path in tarball archive")?
+                .file_name()
+                .context("invalid path in tarball archive")?
+                .to_string_lossy()
+                == "install.yml",
+            "the first file is not rinstall spec file"
+        );
+        let mut spec_file = String::new();
+        entry
+            .read_to_string(&mut spec_file)
+            .context("unable to read spec file from tarball")?;
+
+        // The metadata file is optional, tarballs created by older versions
+        // of rinstall do not contain it
+        let mut metadata = TarballMetadata::default();
+        if let Some(entry) = tarball_entries.next() {
+            let mut entry = entry.context("invalid tarball archive")?;
+            let is_metadata = entry
+                .path()
+                .context("invalid path in tarball archive")?
+                .file_name()
+                .is_some_and(|name| name == METADATA_FILE);
+            if is_metadata {
+                let mut contents = String::new();
+                entry
+                    .read_to_string(&mut contents)
+                    .context("unable to read metadata from tarball")?;
+                metadata = serde_yaml::from_str(&contents)
+                    .context("unable to parse metadata from tarball")?;
+            }
+        }
+
+        Ok(Self {
+            buf,
+            spec_file,
+            metadata,
+        })
+    }
+
+    pub fn archive(&self) -> tar::Archive<&[u8]> {
+        tar::Archive::new(&self.buf[..])
+    }
+
+    /// Return the path of an entry relative to the project directory, or None
+    /// if it is part of the payload of another target triple
+    pub fn payload_path(
+        &self,
+        path: &Utf8Path,
+        rust_target_triple: Option<&str>,
+    ) -> Option<Utf8PathBuf> {
+        // Strip the inner directory, i.e. "rinstall-0.3.0/"
+        let path = strip_directory_name(path);
+        if let Some(payload) = rust_target_triple.and_then(|triple| path.strip_prefix(triple).ok())
+        {
+            Some(payload.to_path_buf())
+        } else if self
+            .metadata
+            .rust_target_triples
+            .iter()
+            .any(|triple| path.starts_with(triple))
+        {
+            None
+        } else {
+            Some(path)
+        }
+    }
+}
+
+/// Append a file with the given contents to the archive
+pub fn append_data(
+    archive: &mut tar::Builder<Vec<u8>>,
+    path: &Utf8Path,
+    contents: &[u8],
+) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs()),
+    );
+    archive
+        .append_data(&mut header, path, contents)
+        .with_context(|| format!("Unable to append file {path} to tarball"))
+}
+
+/// Compress the archive and write it into the filesystem
+pub fn write_tarball(
+    archive: tar::Builder<Vec<u8>>,
+    filename: &Utf8Path,
+) -> Result<()> {
+    if filename.exists() {
+        fs::remove_file(filename).with_context(|| format!("unable to remove file {filename}"))?;
+    }
+    fs::write(filename, gzip_archive(archive)?)
+        .with_context(|| format!("Unable to write compressed tarball {filename} into filesystem"))
+}
+
+/// Returns the compressed content of the archive
+pub fn gzip_archive(archive: tar::Builder<Vec<u8>>) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(&archive.into_inner().context("unable to create tarball")?)
+        .context("Unable to compress tarball")?;
+    encoder.finish().context("Unable to compress tarball")
+}
+
+/// Remove the inner directory from the path of an entry
+/// i.e. "rinstall-0.3.0/rinstall" becomes "rinstall"
+pub fn strip_directory_name(path: &Utf8Path) -> Utf8PathBuf {
+    let mut components = path.components();
+    components.next();
+    components.as_path().to_path_buf()
+}
@@ -9,9 +9,19 @@ pub struct TarballCmd {
     #[clap(
         long,
         help = concat!("Use the generated binaries and libraries from this",
-                       " target triple (only effective for rust projects)")
+                       " cargo profile, `dev` uses the debug directory",
+                       " (only effective for rust projects)"),
+        conflicts_with = "rust_debug_target"
     )]
-    pub rust_target_triple: Option<String>,
+    pub rust_profile: Option<String>,
+    #[clap(
+        long,
+        help = concat!("Use the generated binaries and libraries from this",
+                       " target triple (only effective for rust projects). Pass it",
+                       " multiple times to bundle the payloads of multiple target triples"),
+        env = "CARGO_BUILD_TARGET"
+    )]
+    pub rust_target_triple: Vec<String>,
     #[clap(
         short = 'P',
         long,
@@ -20,6 +30,12 @@ pub struct TarballCmd {
             .expect("unable to get current directory"),
     )]
     pub package_dir: std::path::PathBuf,
+    #[clap(
+        long,
+        help = concat!("Path to the build directory of meson, cmake and go projects,",
+                       " relative to the current directory")
+    )]
+    pub build_dir: Option<String>,
     #[clap(
         short = 'p',
         long = "pkgs",
@@ -36,4 +52,9 @@ pub struct TarballCmd {
         help = "Name of the tarball to create (the suffix .tar.gz is added if not present)"
     )]
     pub tarball_name: String,
+    #[clap(
+        long,
+        help = "Sign the tarball with this secret key, creating a detached signature (.sig)"
+    )]
+    pub sign: Option<std::path::PathBuf>,
 }
@@ -1,25 +1,28 @@
-use std::{
-    fs::{self, File},
-    io::Write,
-};
+use std::{collections::HashSet, fs};
 
-use camino::Utf8Path;
+use camino::{Utf8Path, Utf8PathBuf};
 use clap::Args;
 use color_eyre::{
-    eyre::{Context, ContextCompat},
+    eyre::{bail, Context, ContextCompat},
     owo_colors::OwoColorize,
     Result,
 };
 use colored::Colorize;
-use flate2::{write::GzEncoder, Compression};
 use log::info;
 use walkdir::WalkDir;
 
 use crate::{
     dirs::Dirs,
     install_spec::InstallSpec,
+    install_target::{EntryKind, InstallEntry},
+    man_page::{page_name, ManFormat},
     package::{CompletionsToInstall, Type},
-    project::{RustDirectories, RUST_DIRECTORIES, RUST_DIRECTORIES_ONCE},
+    project::{
+        run_generate_command, set_build_directories, set_default_directories, set_rust_directories,
+        DefaultProjectDirectories, RustDirectories,
+    },
+    signature::SecretKey,
+    tarball::{append_data, runs_on_host, write_tarball, TarballMetadata, METADATA_FILE},
     DirsConfig,
 };
 
@@ -29,8 +32,6 @@ impl TarballCmd {
     pub fn run(self) -> Result<()> {
         let dirs_config = DirsConfig::load(None, true, &mut DirsConfig::system_config())?;
         let dirs = Dirs::new(dirs_config, true).context("unable to create dirs")?;
-        let install_spec =
-            InstallSpec::new_from_path(Utf8Path::from_path(&self.package_dir).unwrap())?;
 
         let package_dir = Utf8Path::from_path(&self.package_dir)
             .context("Package directory contains invalid UTF-8 character")?;
@@ -45,104 +46,218 @@ impl TarballCmd {
 
         let directory_name = self.directory_name.as_deref().unwrap_or(&self.tarball_name);
 
-        // Add install.yml
+        // Add install.yml, which might have been generated from Cargo.toml
         info!("Adding install.yml");
-        archive
-            .append_path_with_name(
-                package_dir.join("install.yml"),
-                format!("{directory_name}/install.yml",),
-            )
-            .context("Unable to append file install.yml to tarball")?;
-
-        let rinstall_version = install_spec.version.clone();
-        let packages = install_spec.packages(&self.packages);
-
-        if packages.iter().any(|p| p.pkg_type == Type::Rust) {
-            RUST_DIRECTORIES_ONCE.call_once(|| {
-                // We use call_once on std::once::Once, this is safe
-                unsafe {
-                    RUST_DIRECTORIES = Some(
-                        RustDirectories::new(
-                                    Some(package_dir.to_path_buf())
-                            ,
-                            self.rust_debug_target,
-                            self.rust_target_triple.as_deref(),
-                        )
-                        // TODO
-                        .unwrap(),
-                    );
-                }
-            });
-        }
+        let (_, spec_file) = InstallSpec::read_spec_file(package_dir)?;
+        // The cargo project is not available when installing from the tarball
+        let spec_file = InstallSpec::expand_auto_entries(
+            &spec_file,
+            &self.packages,
+            &RustDirectories::new(
+                Some(package_dir.to_path_buf()),
+                self.rust_debug_target,
+                self.rust_profile.as_deref(),
+                self.rust_target_triple.first().map(String::as_str),
+            )?,
+        )?;
+        append_data(
+            &mut archive,
+            &Utf8Path::new(directory_name).join("install.yml"),
+            spec_file.as_bytes(),
+        )?;
 
-        for package in packages {
-            info!(
-                "{} {} {}",
-                ">>>".magenta(),
-                "Package".bright_black(),
-                package.name.as_ref().unwrap().italic().blue()
+        // With multiple target triples, each payload is stored in its own directory
+        let metadata = TarballMetadata::new(if self.rust_target_triple.len() > 1 {
+            self.rust_target_triple.clone()
+        } else {
+            Vec::new()
+        });
+        let metadata_contents =
+            serde_yaml::to_string(&metadata).context("unable to serialize tarball metadata")?;
+        append_data(
+            &mut archive,
+            &Utf8Path::new(directory_name).join(METADATA_FILE),
+            metadata_contents.as_bytes(),
+        )?;
+
+        let rust_target_triples = if metadata.rust_target_triples.is_empty() {
+            vec![self.rust_target_triple.first().map(String::as_str)]
+        } else {
+            metadata
+                .rust_target_triples
+                .iter()
+                .map(|triple| Some(triple.as_str()))
+                .collect()
+        };
+
+        // The completions are generated by running the executable built for this host
+        let host_triple = rust_target_triples
+            .iter()
+            .find(|triple| triple.is_none_or(runs_on_host))
+            .copied();
+
+        // Architecture independent files are shared by all the target triples
+        let mut added_sources = HashSet::new();
+        for rust_target_triple in rust_target_triples {
+            let install_spec = InstallSpec::new_from_path(package_dir)?;
+            let rinstall_version = install_spec.version.clone();
+            let packages = install_spec.packages(&self.packages);
+
+            set_default_directories(DefaultProjectDirectories {
+                packagedir: Some(package_dir.to_path_buf()),
+            });
+            let outputdir = if packages.iter().any(|p| p.pkg_type == Type::Rust) {
+                let rust_directories = RustDirectories::new(
+                    Some(package_dir.to_path_buf()),
+                    self.rust_debug_target,
+                    self.rust_profile.as_deref(),
+                    rust_target_triple,
+                )?;
+                let outputdir = rust_directories.outputdir.clone();
+                set_rust_directories(rust_directories);
+                outputdir
+            } else {
+                None
+            };
+            set_build_directories(
+                &packages.iter().map(|p| p.pkg_type).collect::<Vec<_>>(),
+                package_dir,
+                self.build_dir.as_deref(),
             );
 
-            let targets =
-                package.targets(&dirs, &rinstall_version, true, &CompletionsToInstall::all())?;
+            if let Some(rust_target_triple) = rust_target_triple {
+                info!(
+                    "{} {} {}",
+                    ">>>".magenta(),
+                    "Target".bright_black(),
+                    rust_target_triple.italic().blue()
+                );
+            }
 
-            for install_entry in &targets {
-                // Print each file/directory added
-                info!("Adding {}", install_entry.source.as_str().bold().magenta());
-                archive
-                    .append_path_with_name(
-                        &install_entry.full_source,
-                        format!("{directory_name}/{}", install_entry.source),
-                    )
-                    .with_context(|| {
-                        format!("Unable to append path {} to tarball", install_entry.source)
-                    })?;
-                if install_entry.full_source.is_dir() {
-                    WalkDir::new(&install_entry.full_source)
-                        .into_iter()
-                        .try_for_each(|entry| -> Result<()> {
-                            let entry = entry?;
-                            if !entry.file_type().is_file() {
-                                // skip directories
-                                return Ok(());
-                            }
-                            let full_file_path = Utf8Path::from_path(entry.path()).unwrap();
-                            // unwrap here is unsafe
-                            let relative_file_path = full_file_path
-                                .strip_prefix(&install_entry.full_source)
-                                .unwrap();
-                            let source = install_entry.source.join(relative_file_path);
-
-                            info!("Adding {}", source.as_str().bold().magenta());
-                            archive
-                                .append_path_with_name(
-                                    full_file_path,
-                                    format!("{directory_name}/{source}",),
-                                )
-                                .with_context(|| {
-                                    format!("Unable to append path {source} to tarball")
-                                })?;
-
-                            Ok(())
-                        })?;
+            for package in packages {
+                info!(
+                    "{} {} {}",
+                    ">>>".magenta(),
+                    "Package".bright_black(),
+                    package.name.as_ref().unwrap().italic().blue()
+                );
+
+                let targets = package.targets(
+                    &dirs,
+                    &rinstall_version,
+                    true,
+                    &CompletionsToInstall::all(),
+                )?;
+
+                for install_entry in &targets {
+                    install_entry.ensure_source_exists()?;
+                    if install_entry.generate.is_some() {
+                        let Some(host_triple) = host_triple else {
+                            bail!(
+                                "the completions {:?} are generated by running the executable, which is not built for this host",
+                                install_entry.source
+                            );
+                        };
+                        if rust_target_triple != host_triple {
+                            continue;
+                        }
+                    }
+                    // The generated files are shared, even if the executable is a build output
+                    let is_build_output = install_entry.generate.is_none()
+                        && outputdir.as_ref().is_some_and(|outputdir| {
+                            install_entry.full_source.starts_with(outputdir)
+                        });
+                    let prefix = if is_build_output && !metadata.rust_target_triples.is_empty() {
+                        Utf8Path::new(directory_name).join(rust_target_triple.unwrap())
+                    } else if added_sources.insert(install_entry.source.clone()) {
+                        Utf8PathBuf::from(directory_name)
+                    } else {
+                        // It has already been added for a previous target triple
+                        continue;
+                    };
+                    append_entry(&mut archive, install_entry, &prefix, package_dir)?;
                 }
             }
         }
 
-        if Utf8Path::new(&filename).exists() {
-            fs::remove_file(&filename)
-                .with_context(|| format!("unable to remove file {filename}"))?;
+        write_tarball(archive, Utf8Path::new(&filename))?;
+
+        if let Some(secret_key) = &self.sign {
+            let secret_key = Utf8Path::from_path(secret_key)
+                .with_context(|| format!("{secret_key:?} contains invalid UTF-8 characters"))?;
+            let signature = SecretKey::load(secret_key)?.sign_file(Utf8Path::new(&filename))?;
+            info!(
+                "Signed tarball into {}",
+                signature.as_str().italic().yellow()
+            );
         }
-        let file =
-            File::create(&filename).with_context(|| format!("Unable to create file {filename}"))?;
 
-        let mut encoder = GzEncoder::new(file, Compression::default());
-        //.context("unable to compress tarball")?;
+        Ok(())
+    }
+}
+
+fn append_entry(
+    archive: &mut tar::Builder<Vec<u8>>,
+    install_entry: &InstallEntry,
+    prefix: &Utf8Path,
+    package_dir: &Utf8Path,
+) -> Result<()> {
+    // Print each file/directory added
+    info!("Adding {}", install_entry.source.as_str().bold().magenta());
+    // Add the output of the command in place of the generated file
+    if let Some(command) = &install_entry.generate {
+        let output = run_generate_command(package_dir, command)?;
+        return append_data(archive, &prefix.join(&install_entry.source), &output);
+    }
+    // Add the rendered man page in place of its source
+    if let Some(format) =
+        ManFormat::from_path(&install_entry.source).filter(|_| install_entry.kind == EntryKind::Man)
+    {
+        let source = fs::read_to_string(&install_entry.full_source)
+            .with_context(|| format!("unable to read file {}", install_entry.source))?;
+        let destination_for_file = install_entry.destination_for_file();
+        let contents = format
+            .render(
+                &source,
+                page_name(destination_for_file.file_name().unwrap()),
+            )
+            .with_context(|| format!("unable to render man page {}", install_entry.source))?;
+        return append_data(
+            archive,
+            &prefix.join(&install_entry.source),
+            contents.as_bytes(),
+        );
+    }
+    archive
+        .append_path_with_name(
+            &install_entry.full_source,
+            prefix.join(&install_entry.source),
+        )
+        .with_context(|| format!("Unable to append path {} to tarball", install_entry.source))?;
+    if install_entry.full_source.is_dir() {
+        WalkDir::new(&install_entry.full_source)
+            .into_iter()
+            .try_for_each(|entry| -> Result<()> {
+                let entry = entry?;
+                if !entry.file_type().is_file() {
+                    // skip directories
+                    return Ok(());
+                }
+                let full_file_path = Utf8Path::from_path(entry.path()).unwrap();
+                // unwrap here is unsafe
+                let relative_file_path = full_file_path
+                    .strip_prefix(&install_entry.full_source)
+                    .unwrap();
+                let source = install_entry.source.join(relative_file_path);
 
-        encoder
-            .write_all(&archive.into_inner().context("unable to create tarball")?)
-            .context("Unable to write compressed tarball into filesystem")?;
+                info!("Adding {}", source.as_str().bold().magenta());
+                archive
+                    .append_path_with_name(full_file_path, prefix.join(&source))
+                    .with_context(|| format!("Unable to append path {source} to tarball"))?;
 
-        Ok(())
+                Ok(())
+            })?;
     }
+
+    Ok(())
 }
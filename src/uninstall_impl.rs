@@ -1,11 +1,7 @@
 use std::fs;
 
-use camino::{Utf8Path, Utf8PathBuf};
 use clap::Parser;
-use color_eyre::{
-    eyre::{ensure, Context},
-    Result,
-};
+use color_eyre::{eyre::Context, Result};
 use colored::Colorize;
 use log::{info, warn};
 
@@ -26,18 +22,7 @@ impl Uninstall {
         let dirs = Dirs::new(dirs_config, self.system).context("unable to create dirs")?;
         let dry_run = !self.accept_changes;
         for pkg in &self.packages {
-            let pkg_info = if Utf8Path::new(&pkg).is_absolute() {
-                Utf8PathBuf::from(pkg)
-            } else {
-                dirs.localstatedir
-                    .join("rinstall")
-                    .join(format!("{}.pkg", &pkg))
-            };
-            ensure!(pkg_info.exists(), "package {} is not installed", &pkg);
-            let pkg_info: PackageInfo = serde_yaml::from_str(
-                &fs::read_to_string(&pkg_info)
-                    .with_context(|| format!("unable to read file {:?}", &pkg_info))?,
-            )?;
+            let pkg_info = PackageInfo::load(pkg, &dirs)?;
 
             for file in &pkg_info.files {
                 let modified = file.has_been_modified()?;